@@ -0,0 +1,8 @@
+CREATE TABLE issue_delivery_queue
+(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id       uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    PRIMARY KEY (newsletter_issue_id, subscriber_id)
+);
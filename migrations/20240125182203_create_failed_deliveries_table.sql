@@ -0,0 +1,11 @@
+CREATE TABLE failed_deliveries
+(
+    newsletter_issue_id uuid        NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id       uuid        NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    n_attempts          SMALLINT    NOT NULL,
+    last_error          TEXT        NOT NULL,
+    failed_at           timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_id)
+);
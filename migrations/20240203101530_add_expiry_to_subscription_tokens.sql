@@ -0,0 +1,9 @@
+-- Tokens issued before this migration get a fresh day to be used.
+ALTER TABLE subscription_tokens
+    ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+    ADD COLUMN expires_at timestamptz NOT NULL DEFAULT now() + interval '1 day',
+    ADD COLUMN consumed_at timestamptz NULL;
+ALTER TABLE subscription_tokens
+    ALTER COLUMN created_at DROP DEFAULT,
+    ALTER COLUMN expires_at DROP DEFAULT;
+CREATE INDEX subscription_tokens_expires_at_idx ON subscription_tokens (expires_at);
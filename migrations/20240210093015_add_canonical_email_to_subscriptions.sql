@@ -0,0 +1,28 @@
+-- Addresses that only differ in case belong to the same subscriber. Rows stored before this
+-- migration only have their case folded: punycode conversion happens in the application.
+ALTER TABLE subscriptions
+    ADD COLUMN canonical_email TEXT NULL;
+UPDATE subscriptions
+SET canonical_email = lower(trim(email));
+
+-- Existing rows may now collide: keep the confirmed subscription, or else the oldest one.
+CREATE TEMPORARY TABLE duplicate_subscriptions AS
+SELECT id
+FROM (
+    SELECT id,
+           row_number() OVER (
+               PARTITION BY canonical_email
+               ORDER BY status = 'confirmed' DESC, subscribed_at, id
+           ) AS rank
+    FROM subscriptions
+) AS ranked
+WHERE rank > 1;
+DELETE FROM subscription_tokens
+WHERE subscriber_id IN (SELECT id FROM duplicate_subscriptions);
+DELETE FROM subscriptions
+WHERE id IN (SELECT id FROM duplicate_subscriptions);
+DROP TABLE duplicate_subscriptions;
+
+ALTER TABLE subscriptions
+    ALTER COLUMN canonical_email SET NOT NULL;
+CREATE UNIQUE INDEX subscriptions_canonical_email_idx ON subscriptions (canonical_email);
@@ -0,0 +1,65 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use actix_web::dev::Payload;
+use actix_web::http::header::{HeaderMap, AUTHORIZATION};
+use actix_web::{web, FromRequest, HttpRequest};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use secrecy::Secret;
+use sqlx::PgPool;
+
+use crate::authentication::{validate_credentials, AuthError, Credentials, UserId};
+
+#[derive(Debug)]
+pub struct BasicAuthUser(UserId);
+
+impl BasicAuthUser {
+    pub fn user_id(&self) -> UserId {
+        self.0
+    }
+}
+
+impl FromRequest for BasicAuthUser {
+    type Error = AuthError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let credentials = basic_authentication(req.headers());
+        let pool = req.app_data::<web::Data<PgPool>>().cloned();
+
+        Box::pin(async move {
+            let credentials = credentials.map_err(AuthError::InvalidCredentials)?;
+            let pool = pool.context("No database pool registered as app data")?;
+
+            let user_id = validate_credentials(credentials, &pool).await?;
+
+            Ok(Self(user_id))
+        })
+    }
+}
+
+fn basic_authentication(headers: &HeaderMap) -> Result<Credentials> {
+    let header_value = headers
+        .get(AUTHORIZATION)
+        .context("The 'Authorization' header was missing")?
+        .to_str()
+        .context("The 'Authorization' header was not a valid UTF8 string")?;
+    let base64encoded_segment = header_value
+        .strip_prefix("Basic ")
+        .context("The authorization scheme was not 'Basic'")?;
+    let decoded_bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64encoded_segment)
+        .context("Failed to base64-decode 'Basic' credentials")?;
+    let decoded_credentials = String::from_utf8(decoded_bytes)
+        .context("The decoded credential string is not valid UTF8")?;
+
+    let (username, password) = decoded_credentials
+        .split_once(':')
+        .ok_or_else(|| anyhow!("A password must be provided in 'Basic' auth"))?;
+
+    Ok(Credentials {
+        username: username.to_string(),
+        password: Secret::new(password.to_string()),
+    })
+}
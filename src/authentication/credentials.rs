@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use argon2::password_hash::SaltString;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{AuthError, UserId};
+use crate::telemetry::spawn_blocking_with_tracing;
+
+pub struct Credentials {
+    pub username: String,
+    pub password: Secret<String>,
+}
+
+#[tracing::instrument(name = "Validate credentials", skip_all, fields(username = %credentials.username))]
+pub async fn validate_credentials(
+    credentials: Credentials,
+    pool: &PgPool,
+) -> Result<UserId, AuthError> {
+    let mut user_id = None;
+    // Verify against a dummy hash when the user does not exist so that the response time does not
+    // leak which usernames are registered.
+    let mut expected_password_hash = Secret::new(
+        "$argon2id$v=19$m=15000,t=2,p=1$\
+        PrXrs/ahcqgzpVYOioCiYw$\
+        H0j4wwVQA4lY4sJY1Dr+tcL3VQAe1TNwzkLlZDPlrsg"
+            .to_string(),
+    );
+
+    if let Some((stored_user_id, stored_password_hash)) =
+        get_stored_credentials(&credentials.username, pool).await?
+    {
+        user_id = Some(stored_user_id);
+        expected_password_hash = stored_password_hash;
+    }
+
+    spawn_blocking_with_tracing(move || {
+        verify_password_hash(expected_password_hash, credentials.password)
+    })
+    .await
+    .context("Failed to spawn blocking task")??;
+
+    user_id
+        .map(UserId::from)
+        .ok_or_else(|| AuthError::InvalidCredentials(anyhow::anyhow!("Unknown username")))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_stored_credentials(
+    username: &str,
+    pool: &PgPool,
+) -> Result<Option<(Uuid, Secret<String>)>> {
+    let row = sqlx::query!(
+        r#"SELECT user_id, password_hash FROM users WHERE username = $1"#,
+        username,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve stored credentials")?
+    .map(|row| (row.user_id, Secret::new(row.password_hash)));
+
+    Ok(row)
+}
+
+#[tracing::instrument(name = "Verify password hash", skip_all)]
+fn verify_password_hash(
+    expected_password_hash: Secret<String>,
+    password_candidate: Secret<String>,
+) -> Result<(), AuthError> {
+    let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
+        .context("Failed to parse hash in PHC string format")?;
+
+    Argon2::default()
+        .verify_password(
+            password_candidate.expose_secret().as_bytes(),
+            &expected_password_hash,
+        )
+        .context("Invalid password")
+        .map_err(AuthError::InvalidCredentials)
+}
+
+pub fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let password_hash = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(15000, 2, 1, None).context("Invalid Argon2 parameters")?,
+    )
+    .hash_password(password.expose_secret().as_bytes(), &salt)
+    .context("Failed to hash password")?
+    .to_string();
+
+    Ok(Secret::new(password_hash))
+}
+
+#[tracing::instrument(skip(password, pool))]
+pub async fn change_password(
+    user_id: UserId,
+    password: Secret<String>,
+    pool: &PgPool,
+) -> Result<()> {
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await
+        .context("Failed to spawn blocking task")??;
+
+    sqlx::query!(
+        r#"UPDATE users SET password_hash = $1 WHERE user_id = $2"#,
+        password_hash.expose_secret(),
+        user_id.as_ref(),
+    )
+    .execute(pool)
+    .await
+    .context("Failed to change user's password in the database")?;
+
+    Ok(())
+}
+
+/// Creates a user unless one already has this username, whatever its password.
+///
+/// Returns whether the user was created.
+#[tracing::instrument(skip_all, fields(username = %credentials.username))]
+pub async fn create_user_if_missing(credentials: Credentials, pool: &PgPool) -> Result<bool> {
+    let password = credentials.password;
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await
+        .context("Failed to spawn blocking task")??;
+
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (username) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        credentials.username,
+        password_hash.expose_secret(),
+    )
+    .execute(pool)
+    .await
+    .context("Failed to create a user")?
+    .rows_affected()
+        == 1;
+
+    Ok(created)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_username(user_id: UserId, pool: &PgPool) -> Result<String> {
+    let row = sqlx::query!(
+        r#"SELECT username FROM users WHERE user_id = $1"#,
+        user_id.as_ref(),
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve a username")?;
+
+    Ok(row.username)
+}
@@ -0,0 +1,32 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::{FromRequest, HttpMessage, HttpResponse};
+use actix_web_lab::middleware::Next;
+use tracing::error;
+
+use crate::session::TypedSession;
+use crate::utils::see_other;
+
+pub async fn reject_anonymous_users<B: MessageBody>(
+    mut req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let session = {
+        let (http_request, payload) = req.parts_mut();
+        TypedSession::from_request(http_request, payload).await?
+    };
+
+    match session.get_user_id() {
+        Ok(Some(user_id)) => {
+            req.extensions_mut().insert(user_id);
+            Ok(next.call(req).await?.map_into_left_body())
+        }
+        Ok(None) => Ok(req.into_response(see_other("/login")).map_into_right_body()),
+        Err(e) => {
+            error!(?e, "Failed to read the session state");
+            Ok(req
+                .into_response(HttpResponse::InternalServerError().finish())
+                .map_into_right_body())
+        }
+    }
+}
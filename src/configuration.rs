@@ -1,18 +1,37 @@
 use std::env;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use config::{Config, FileFormat};
+use ipnet::IpNet;
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use serde_aux::prelude::*;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use sqlx::ConnectOptions;
 
+use crate::domain::PopularDomains;
+use crate::domain_verification::{
+    DnsDomainVerifier, DomainVerification, FailurePolicy, InMemoryDomainVerifier,
+};
+use crate::email_client::{
+    EmailClient, InMemoryTransport, MailgunTransport, PostmarkTransport, SesTransport,
+    SmtpAuthMechanism, SmtpTls, SmtpTransport,
+};
+use crate::issue_delivery_worker::RetryPolicy;
+use crate::rate_limit::RateLimit;
+
 #[derive(Deserialize, Clone)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
+    pub issue_delivery: IssueDeliverySettings,
+    pub subscription_tokens: SubscriptionTokenSettings,
+    pub email_validation: EmailValidationSettings,
+    pub health: HealthSettings,
+    #[serde(default)]
+    pub telemetry: TelemetrySettings,
+    pub redis_uri: Option<Secret<String>>,
 }
 
 impl Settings {
@@ -39,7 +58,18 @@ impl Settings {
             )
             .build()?;
 
-        Ok(settings.try_deserialize()?)
+        let settings: Self = settings.try_deserialize()?;
+        if let Environment::Production = environment {
+            if settings.application.rate_limit.trusted_proxies.is_empty() {
+                bail!(
+                    "`application.rate_limit.trusted_proxies` must list the load balancer's \
+                    networks in production, e.g. \
+                    APP_APPLICATION__RATE_LIMIT__TRUSTED_PROXIES=10.0.0.0/24"
+                );
+            }
+        }
+
+        Ok(settings)
     }
 }
 
@@ -108,19 +138,352 @@ pub struct ApplicationSettings {
     pub host: String,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
+    /// When set, `/metrics` is served on this port only, instead of next to the public API.
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub admin_port: Option<u16>,
     pub base_url: String,
+    pub session_key: Secret<String>,
+    pub unsubscribe_key: Secret<String>,
+    pub rate_limit: RateLimitSettings,
+    /// How long in-flight requests and the current delivery get to finish once shutdown starts.
+    pub shutdown_deadline_seconds: u64,
+    /// Creates the first admin at startup. Only set it through the environment, e.g.
+    /// `APP_APPLICATION__BOOTSTRAP_ADMIN__PASSWORD`, and unset it once the admin has logged in.
+    #[serde(default)]
+    pub bootstrap_admin: Option<BootstrapAdminSettings>,
+}
+
+/// An admin that is created if no user has its username yet. An existing user is left untouched.
+#[derive(Deserialize, Clone)]
+pub struct BootstrapAdminSettings {
+    pub username: String,
+    pub password: Secret<String>,
+}
+
+impl ApplicationSettings {
+    pub fn shutdown_deadline(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.shutdown_deadline_seconds)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RateLimitSettings {
+    #[serde(default)]
+    pub store: RateLimitStoreKind,
+    /// Networks of the proxies whose `X-Forwarded-For` header is trusted to carry the client
+    /// address, e.g. `10.0.0.0/8`. A single proxy is written `10.0.0.1/32`. Environment
+    /// variables list them separated by commas.
+    #[serde(default, deserialize_with = "deserialize_networks")]
+    pub trusted_proxies: Vec<IpNet>,
+    pub per_ip: RateLimitPolicySettings,
+    pub per_email: RateLimitPolicySettings,
+}
+
+/// `in_memory` keeps buckets in each process. `redis` shares them through `redis_uri`.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitStoreKind {
+    #[default]
+    InMemory,
+    Redis,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RateLimitPolicySettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub capacity: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub refill_interval_seconds: u64,
+}
+
+impl RateLimitPolicySettings {
+    pub fn limit(&self) -> RateLimit {
+        RateLimit {
+            capacity: self.capacity,
+            refill_interval: std::time::Duration::from_secs(self.refill_interval_seconds),
+        }
+    }
 }
 
 #[derive(Deserialize, Clone)]
 pub struct EmailClientSettings {
-    pub base_url: String,
+    #[serde(default)]
+    pub kind: EmailTransportKind,
     pub sender_email: String,
-    pub authorization_token: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub base_url: String,
+    pub authorization_token: Secret<String>,
+    pub smtp: Option<SmtpSettings>,
+    pub ses: Option<SesSettings>,
+    pub mailgun: Option<MailgunSettings>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTransportKind {
+    #[default]
+    Postmark,
+    Smtp,
+    Ses,
+    Mailgun,
+    InMemory,
+}
+
+/// Only read when `email_client.kind` is `smtp`.
+#[derive(Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    #[serde(default = "default_smtp_auth_mechanisms")]
+    pub auth_mechanisms: Vec<SmtpAuthMechanism>,
+}
+
+/// Only read when `email_client.kind` is `ses`.
+#[derive(Deserialize, Clone)]
+pub struct SesSettings {
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: Secret<String>,
+    /// Overrides the regional endpoint, e.g. to point at a mock server.
+    pub base_url: Option<String>,
+}
+
+/// Only read when `email_client.kind` is `mailgun`.
+#[derive(Deserialize, Clone)]
+pub struct MailgunSettings {
+    pub domain: String,
+    pub api_key: Secret<String>,
+    #[serde(default = "default_mailgun_base_url")]
+    pub base_url: String,
+}
+
+fn deserialize_networks<'de, D>(deserializer: D) -> Result<Vec<IpNet>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Networks {
+        List(Vec<IpNet>),
+        CommaSeparated(String),
+    }
+
+    match Networks::deserialize(deserializer)? {
+        Networks::List(networks) => Ok(networks),
+        Networks::CommaSeparated(networks) => networks
+            .split(',')
+            .map(str::trim)
+            .filter(|network| !network.is_empty())
+            .map(|network| network.parse().map_err(serde::de::Error::custom))
+            .collect(),
+    }
+}
+
+fn default_mailgun_base_url() -> String {
+    "https://api.mailgun.net".into()
+}
+
+fn default_smtp_auth_mechanisms() -> Vec<SmtpAuthMechanism> {
+    vec![SmtpAuthMechanism::Plain, SmtpAuthMechanism::Login]
 }
 
 impl EmailClientSettings {
+    pub fn client(&self) -> Result<EmailClient> {
+        let sender = self.sender_email.clone();
+        let client = match self.kind {
+            EmailTransportKind::Postmark => EmailClient::new(
+                sender,
+                PostmarkTransport::new(
+                    self.base_url.clone(),
+                    self.authorization_token.clone(),
+                    self.timeout(),
+                ),
+            ),
+            EmailTransportKind::Smtp => {
+                let smtp = self
+                    .smtp
+                    .as_ref()
+                    .context("`email_client.smtp` must be set to use the SMTP transport")?;
+                EmailClient::new(sender, SmtpTransport::new(smtp, self.timeout())?)
+            }
+            EmailTransportKind::Ses => {
+                let ses = self
+                    .ses
+                    .as_ref()
+                    .context("`email_client.ses` must be set to use the SES transport")?;
+                EmailClient::new(
+                    sender,
+                    SesTransport::new(
+                        ses.region.clone(),
+                        ses.access_key_id.clone(),
+                        ses.secret_access_key.clone(),
+                        ses.base_url.clone(),
+                        self.timeout(),
+                    )?,
+                )
+            }
+            EmailTransportKind::Mailgun => {
+                let mailgun = self
+                    .mailgun
+                    .as_ref()
+                    .context("`email_client.mailgun` must be set to use the Mailgun transport")?;
+                EmailClient::new(
+                    sender,
+                    MailgunTransport::new(
+                        mailgun.base_url.clone(),
+                        mailgun.domain.clone(),
+                        mailgun.api_key.clone(),
+                        self.timeout(),
+                    )?,
+                )
+            }
+            EmailTransportKind::InMemory => EmailClient::new(sender, InMemoryTransport::default()),
+        };
+
+        Ok(client)
+    }
+
     pub fn timeout(&self) -> std::time::Duration {
         std::time::Duration::from_millis(self.timeout_milliseconds)
     }
 }
+
+#[derive(Deserialize, Clone)]
+pub struct IssueDeliverySettings {
+    pub max_attempts: i16,
+    pub base_backoff_milliseconds: u64,
+    pub max_backoff_milliseconds: u64,
+}
+
+impl IssueDeliverySettings {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts,
+            base_backoff: std::time::Duration::from_millis(self.base_backoff_milliseconds),
+            max_backoff: std::time::Duration::from_millis(self.max_backoff_milliseconds),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SubscriptionTokenSettings {
+    pub expiry_hours: i64,
+    pub cleanup_interval_seconds: u64,
+}
+
+impl SubscriptionTokenSettings {
+    pub fn expiry(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.expiry_hours)
+    }
+
+    pub fn cleanup_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.cleanup_interval_seconds)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct EmailValidationSettings {
+    /// A file listing the disposable email domains that subscriptions are not accepted from.
+    pub disposable_domains_file: String,
+    pub domain_verification: DomainVerificationSettings,
+    /// Rejected addresses on a domain within `max_suggestion_distance` edits of one of these get
+    /// a suggested correction. The most popular domains should come first, as they win ties.
+    #[serde(default)]
+    pub popular_domains: Vec<String>,
+    pub max_suggestion_distance: usize,
+}
+
+impl EmailValidationSettings {
+    pub fn popular_domains(&self) -> PopularDomains {
+        PopularDomains::new(self.popular_domains.clone(), self.max_suggestion_distance)
+    }
+}
+
+/// Checks that the domain of a new subscriber's address can receive email.
+#[derive(Deserialize, Clone)]
+pub struct DomainVerificationSettings {
+    #[serde(default)]
+    pub kind: DomainVerifierKind,
+    pub timeout_milliseconds: u64,
+    pub cache_ttl_seconds: u64,
+    #[serde(default)]
+    pub failure_policy: FailurePolicy,
+    /// Only read when `kind` is `in_memory`.
+    #[serde(default)]
+    pub undeliverable_domains: Vec<String>,
+}
+
+/// `dns` looks up MX records through the system's resolver. `in_memory` is for tests and local
+/// development.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainVerifierKind {
+    #[default]
+    Dns,
+    InMemory,
+}
+
+impl DomainVerificationSettings {
+    pub fn verification(&self) -> Result<DomainVerification> {
+        let timeout = std::time::Duration::from_millis(self.timeout_milliseconds);
+        let cache_ttl = std::time::Duration::from_secs(self.cache_ttl_seconds);
+        let verification = match self.kind {
+            DomainVerifierKind::Dns => DomainVerification::new(
+                DnsDomainVerifier::from_system_conf()?,
+                cache_ttl,
+                timeout,
+                self.failure_policy,
+            ),
+            DomainVerifierKind::InMemory => DomainVerification::new(
+                InMemoryDomainVerifier::new(self.undeliverable_domains.clone()),
+                cache_ttl,
+                timeout,
+                self.failure_policy,
+            ),
+        };
+
+        Ok(verification)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct HealthSettings {
+    /// How long each readiness check may take before its component is reported as down.
+    pub timeout_milliseconds: u64,
+    /// Whether readiness depends on the email provider answering.
+    #[serde(default)]
+    pub check_email_provider: bool,
+}
+
+impl HealthSettings {
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct TelemetrySettings {
+    /// Spans are only exported when this is set.
+    pub otlp: Option<OtlpSettings>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct OtlpSettings {
+    /// The collector's base URL, e.g. `http://localhost:4317` for gRPC or `:4318` for HTTP.
+    pub endpoint: String,
+    #[serde(default)]
+    pub protocol: OtlpProtocol,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OtlpProtocol {
+    #[default]
+    Grpc,
+    Http,
+}
@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::domain::validation::ValidationError;
+
+/// Domains that hand out throwaway addresses, which we do not accept subscriptions from.
+#[derive(Debug, Default)]
+pub struct DisposableDomains(HashSet<String>);
+
+impl DisposableDomains {
+    /// Reads one domain per line. Blank lines and lines starting with `#` are ignored.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "Failed to read the disposable email domains from {}",
+                path.display()
+            )
+        })?;
+
+        Ok(contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect())
+    }
+
+    /// Rejects addresses on a blocked domain or on any of its subdomains.
+    pub fn check(&self, email: &SubscriberEmail) -> Result<(), ValidationError> {
+        let mut domain = email.domain();
+        loop {
+            if self.0.contains(domain) {
+                return Err(ValidationError::DisposableDomain {
+                    domain: domain.to_string(),
+                });
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a str> for DisposableDomains {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(domains: I) -> Self {
+        // Entries are normalized like the domains of the addresses they are compared with.
+        Self(
+            domains
+                .into_iter()
+                .map(|domain| {
+                    idna::domain_to_ascii(domain).unwrap_or_else(|_| domain.to_lowercase())
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests;
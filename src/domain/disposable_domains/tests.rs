@@ -0,0 +1,59 @@
+use anyhow::Result;
+use claims::{assert_err, assert_ok};
+
+use crate::domain::{DisposableDomains, SubscriberEmail, ValidationError};
+
+fn email(address: &str) -> SubscriberEmail {
+    SubscriberEmail::try_from(address.to_string()).unwrap()
+}
+
+#[test]
+fn addresses_on_a_blocked_domain_are_rejected() {
+    let blocklist = DisposableDomains::from_iter(["mailinator.com"]);
+
+    assert_eq!(
+        blocklist.check(&email("ursula@Mailinator.com")),
+        Err(ValidationError::DisposableDomain {
+            domain: "mailinator.com".into()
+        })
+    );
+}
+
+#[test]
+fn subdomains_of_a_blocked_domain_are_rejected() {
+    let blocklist = DisposableDomains::from_iter(["mailinator.com"]);
+
+    assert_err!(blocklist.check(&email("ursula@eu.mailinator.com")));
+}
+
+#[test]
+fn other_domains_are_accepted() {
+    let blocklist = DisposableDomains::from_iter(["mailinator.com"]);
+
+    assert_ok!(blocklist.check(&email("ursula@example.com")));
+    assert_ok!(blocklist.check(&email("ursula@notmailinator.com")));
+}
+
+#[test]
+fn internationalized_entries_match_their_punycode_form() {
+    let blocklist = DisposableDomains::from_iter(["Wegwerf-Bücher.example"]);
+
+    assert_err!(blocklist.check(&email("ursula@wegwerf-bücher.example")));
+}
+
+#[test]
+fn the_shipped_blocklist_can_be_loaded() -> Result<()> {
+    let blocklist = DisposableDomains::from_file("configuration/disposable_domains.txt")?;
+
+    assert_err!(blocklist.check(&email("ursula@mailinator.com")));
+    assert_ok!(blocklist.check(&email("ursula@example.com")));
+
+    Ok(())
+}
+
+#[test]
+fn a_missing_file_is_an_error() {
+    assert_err!(DisposableDomains::from_file(
+        "configuration/does-not-exist.txt"
+    ));
+}
@@ -0,0 +1,32 @@
+use anyhow::{ensure, Result};
+
+#[derive(Debug)]
+pub struct IdempotencyKey(String);
+
+impl IdempotencyKey {
+    const MAX_LENGTH: usize = 50;
+}
+
+impl TryFrom<String> for IdempotencyKey {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ensure!(!value.is_empty(), "The idempotency key cannot be empty");
+        ensure!(
+            value.len() <= Self::MAX_LENGTH,
+            "The idempotency key must be shorter than {} characters",
+            Self::MAX_LENGTH
+        );
+
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for IdempotencyKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,32 @@
+use anyhow::Result;
+use claims::assert_err;
+
+use crate::domain::IdempotencyKey;
+
+#[test]
+fn empty_string_is_rejected() {
+    let key = "".to_string();
+    assert_err!(IdempotencyKey::try_from(key));
+}
+
+#[test]
+fn a_50_characters_long_key_is_valid() -> Result<()> {
+    let key = "a".repeat(50);
+    IdempotencyKey::try_from(key)?;
+
+    Ok(())
+}
+
+#[test]
+fn a_key_longer_than_50_characters_is_rejected() {
+    let key = "a".repeat(51);
+    assert_err!(IdempotencyKey::try_from(key));
+}
+
+#[test]
+fn a_uuid_is_a_valid_key() -> Result<()> {
+    let key = uuid::Uuid::new_v4().to_string();
+    IdempotencyKey::try_from(key)?;
+
+    Ok(())
+}
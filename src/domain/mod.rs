@@ -1,7 +1,15 @@
+pub use disposable_domains::*;
+pub use idempotency_key::*;
 pub use new_subscriber::*;
+pub use popular_domains::*;
 pub use subscriber_email::*;
 pub use subscriber_name::*;
+pub use validation::*;
 
+mod disposable_domains;
+mod idempotency_key;
 mod new_subscriber;
+mod popular_domains;
 mod subscriber_email;
 mod subscriber_name;
+mod validation;
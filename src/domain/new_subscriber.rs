@@ -1,7 +1,8 @@
-use anyhow::Result;
-
+use crate::domain::disposable_domains::DisposableDomains;
+use crate::domain::popular_domains::PopularDomains;
 use crate::domain::subscriber_email::SubscriberEmail;
 use crate::domain::subscriber_name::SubscriberName;
+use crate::domain::validation::{FieldError, FieldErrors};
 use crate::routes::SubscribeFormData;
 
 pub struct NewSubscriber {
@@ -9,13 +10,29 @@ pub struct NewSubscriber {
     pub name: SubscriberName,
 }
 
-impl TryFrom<SubscribeFormData> for NewSubscriber {
-    type Error = anyhow::Error;
-
-    fn try_from(value: SubscribeFormData) -> Result<Self> {
-        let email = value.email.try_into()?;
-        let name = value.name.try_into()?;
+impl NewSubscriber {
+    /// Returns every rule broken by every field, suggesting a correction for email addresses
+    /// on a misspelt popular domain.
+    pub fn parse(
+        value: SubscribeFormData,
+        disposable_domains: &DisposableDomains,
+        popular_domains: &PopularDomains,
+    ) -> Result<Self, Vec<FieldError>> {
+        let mut errors = FieldErrors::default();
+        let suggestion = popular_domains.suggest(&value.email);
+        let email = errors.check(
+            "email",
+            SubscriberEmail::try_from(value.email).and_then(|email| {
+                disposable_domains.check(&email)?;
+                Ok(email)
+            }),
+        );
+        errors.suggest("email", suggestion);
+        let name = errors.check("name", SubscriberName::try_from(value.name));
 
-        Ok(Self { email, name })
+        match (email, name) {
+            (Some(email), Some(name)) => Ok(Self { email, name }),
+            _ => Err(errors.into_vec()),
+        }
     }
 }
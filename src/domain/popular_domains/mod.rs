@@ -0,0 +1,39 @@
+/// The domains of widely used email providers, used to spot typos such as `gmial.com`.
+#[derive(Debug, Default)]
+pub struct PopularDomains {
+    domains: Vec<String>,
+    max_edit_distance: usize,
+}
+
+impl PopularDomains {
+    /// Domains earlier in the list win ties, so the most popular should come first.
+    pub fn new(domains: impl IntoIterator<Item = String>, max_edit_distance: usize) -> Self {
+        Self {
+            domains: domains
+                .into_iter()
+                .map(|domain| domain.to_lowercase())
+                .collect(),
+            max_edit_distance,
+        }
+    }
+
+    /// Suggests the address the subscriber most likely meant, if its domain is a near miss of a
+    /// popular one. Transposed letters count as a single edit.
+    pub fn suggest(&self, address: &str) -> Option<String> {
+        let (local_part, domain) = address.trim().rsplit_once('@')?;
+        let domain = domain.to_lowercase();
+        if local_part.is_empty() || self.domains.contains(&domain) {
+            return None;
+        }
+
+        self.domains
+            .iter()
+            .map(|popular| (strsim::osa_distance(&domain, popular), popular))
+            .filter(|&(distance, _)| distance <= self.max_edit_distance)
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, popular)| format!("{}@{}", local_part, popular))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,54 @@
+use crate::domain::PopularDomains;
+
+fn popular_domains() -> PopularDomains {
+    PopularDomains::new(
+        ["gmail.com", "hotmail.com", "yahoo.com", "mail.com"].map(String::from),
+        2,
+    )
+}
+
+#[test]
+fn common_typos_are_corrected() {
+    let popular_domains = popular_domains();
+
+    for (typo, suggestion) in [
+        ("ursula@gmial.com", "ursula@gmail.com"),
+        ("ursula@hotmial.com", "ursula@hotmail.com"),
+        ("ursula@yahoo.con", "ursula@yahoo.com"),
+        ("Ursula@GMAIL.CON", "Ursula@gmail.com"),
+        ("ursula@gmail,com", "ursula@gmail.com"),
+    ] {
+        assert_eq!(
+            popular_domains.suggest(typo).as_deref(),
+            Some(suggestion),
+            "No correction suggested for {}",
+            typo
+        );
+    }
+}
+
+#[test]
+fn the_closest_popular_domain_is_suggested() {
+    // `gmail.com` is two edits away, `mail.com` only one.
+    assert_eq!(
+        popular_domains().suggest("ursula@nail.com").as_deref(),
+        Some("ursula@mail.com")
+    );
+}
+
+#[test]
+fn popular_domains_are_not_corrected() {
+    assert_eq!(popular_domains().suggest("ursula@mail.com"), None);
+    assert_eq!(popular_domains().suggest("ursula@Gmail.com"), None);
+}
+
+#[test]
+fn distant_domains_are_not_corrected() {
+    assert_eq!(popular_domains().suggest("ursula@example.com"), None);
+}
+
+#[test]
+fn addresses_without_a_local_part_or_domain_are_not_corrected() {
+    assert_eq!(popular_domains().suggest("gmial.com"), None);
+    assert_eq!(popular_domains().suggest("@gmial.com"), None);
+}
@@ -1,26 +1,58 @@
-use anyhow::{ensure, Result};
 use validator::validate_email;
 
+use crate::domain::validation::{ValidationError, ValidationErrors};
+
+/// An email address, normalized so that it can be compared and sent to.
+///
+/// Surrounding whitespace is trimmed and the domain is lowercased, with internationalized
+/// domains converted to punycode. The local part keeps its case, as some mail servers honour it.
 #[derive(Debug)]
-pub struct SubscriberEmail(String);
+pub struct SubscriberEmail {
+    address: String,
+    canonical: String,
+}
 
 impl TryFrom<String> for SubscriberEmail {
-    type Error = anyhow::Error;
+    type Error = ValidationErrors;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(ValidationError::Empty.into());
+        }
+
+        let (local_part, domain) = value
+            .rsplit_once('@')
+            .ok_or(ValidationError::InvalidEmail)?;
+        let domain = idna::domain_to_ascii(domain).map_err(|_| ValidationError::InvalidEmail)?;
+        let address = format!("{}@{}", local_part, domain);
+        if !validate_email(&address) {
+            return Err(ValidationError::InvalidEmail.into());
+        }
 
-    fn try_from(value: String) -> Result<Self> {
-        ensure!(
-            validate_email(&value),
-            "{} is not a valid email address",
-            value
-        );
+        Ok(Self {
+            canonical: format!("{}@{}", local_part.to_lowercase(), domain),
+            address,
+        })
+    }
+}
+
+impl SubscriberEmail {
+    /// The lowercased address, used to tell whether two addresses belong to the same subscriber.
+    pub fn canonical(&self) -> &str {
+        &self.canonical
+    }
 
-        Ok(Self(value))
+    /// The ASCII domain, e.g. `xn--bcher-kva.example` for `bücher.example`.
+    pub fn domain(&self) -> &str {
+        // The address was checked to contain an `@` when it was parsed.
+        self.address.rsplit_once('@').unwrap().1
     }
 }
 
 impl AsRef<str> for SubscriberEmail {
     fn as_ref(&self) -> &str {
-        &self.0
+        &self.address
     }
 }
 
@@ -1,3 +1,4 @@
+use anyhow::Result;
 use claims::assert_err;
 use fake::locales;
 use fake::locales::Data;
@@ -5,6 +6,7 @@ use quickcheck::{Arbitrary, Gen};
 use quickcheck_macros::quickcheck;
 
 use super::*;
+use crate::domain::ValidationError;
 
 #[derive(Debug, Clone)]
 struct ValidEmailFixture(pub String);
@@ -44,3 +46,42 @@ fn valid_emails_are_parsed_successfully(valid_email: ValidEmailFixture) -> Resul
 
     Ok(())
 }
+
+#[test]
+fn the_broken_rule_is_reported() {
+    let errors = SubscriberEmail::try_from("ursuladomain.com".to_string()).unwrap_err();
+    assert_eq!(errors.errors(), [ValidationError::InvalidEmail]);
+
+    let errors = SubscriberEmail::try_from(" ".to_string()).unwrap_err();
+    assert_eq!(errors.errors(), [ValidationError::Empty]);
+}
+
+#[test]
+fn addresses_are_trimmed_and_their_domain_lowercased() -> Result<()> {
+    let email = SubscriberEmail::try_from("  Ursula@Example.COM\n".to_string())?;
+
+    assert_eq!(email.as_ref(), "Ursula@example.com");
+    assert_eq!(email.canonical(), "ursula@example.com");
+    assert_eq!(email.domain(), "example.com");
+
+    Ok(())
+}
+
+#[test]
+fn differently_cased_addresses_share_a_canonical_form() -> Result<()> {
+    let first = SubscriberEmail::try_from("Foo@Example.com".to_string())?;
+    let second = SubscriberEmail::try_from("foo@example.com".to_string())?;
+
+    assert_eq!(first.canonical(), second.canonical());
+
+    Ok(())
+}
+
+#[test]
+fn internationalized_domains_are_converted_to_punycode() -> Result<()> {
+    let email = SubscriberEmail::try_from("ursula@Bücher.example".to_string())?;
+
+    assert_eq!(email.as_ref(), "ursula@xn--bcher-kva.example");
+
+    Ok(())
+}
@@ -1,25 +1,41 @@
-use anyhow::{bail, Result};
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::domain::validation::{ValidationError, ValidationErrors};
+
+const MAX_LENGTH: usize = 256;
+const FORBIDDEN_CHARACTERS: [char; 9] = ['/', '(', ')', '"', '<', '>', '\\', '{', '}'];
+
 #[derive(Debug)]
 pub struct SubscriberName(String);
 
 impl TryFrom<String> for SubscriberName {
-    type Error = anyhow::Error;
+    /// Every rule the name breaks.
+    type Error = ValidationErrors;
 
-    fn try_from(value: String) -> Result<Self> {
-        let is_empty_or_whitespace = value.trim().is_empty();
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let mut errors = Vec::new();
 
-        let is_too_long = value.graphemes(true).count() > 256;
-
-        const FORBIDDEN_CHARACTERS: [char; 9] = ['/', '(', ')', '"', '<', '>', '\\', '{', '}'];
-        let contains_forbidden_characters =
-            value.chars().any(|ch| FORBIDDEN_CHARACTERS.contains(&ch));
+        if value.trim().is_empty() {
+            errors.push(ValidationError::Empty);
+        }
 
-        if is_empty_or_whitespace || is_too_long || contains_forbidden_characters {
-            bail!("{} is not a valid subscriber name", value);
+        if value.graphemes(true).count() > MAX_LENGTH {
+            errors.push(ValidationError::TooLong { max: MAX_LENGTH });
         }
 
+        let mut forbidden: Vec<char> = value
+            .chars()
+            .filter(|ch| FORBIDDEN_CHARACTERS.contains(ch))
+            .collect();
+        forbidden.sort_unstable();
+        forbidden.dedup();
+        errors.extend(
+            forbidden
+                .into_iter()
+                .map(|character| ValidationError::ForbiddenCharacter { character }),
+        );
+
+        ValidationErrors::into_result(errors)?;
         Ok(Self(value))
     }
 }
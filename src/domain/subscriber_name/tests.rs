@@ -1,7 +1,7 @@
 use anyhow::Result;
 use claims::assert_err;
 
-use crate::domain::SubscriberName;
+use crate::domain::{SubscriberName, ValidationError};
 
 #[test]
 fn a_256_grapheme_long_name_is_valid() -> Result<()> {
@@ -44,3 +44,32 @@ fn a_valid_name_is_parsed_successfully() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn every_broken_rule_is_reported() {
+    let name = format!("{{{}}}", "ë".repeat(256));
+
+    let errors = SubscriberName::try_from(name).unwrap_err();
+
+    assert_eq!(
+        errors.errors(),
+        [
+            ValidationError::TooLong { max: 256 },
+            ValidationError::ForbiddenCharacter { character: '{' },
+            ValidationError::ForbiddenCharacter { character: '}' },
+        ]
+    );
+}
+
+#[test]
+fn a_repeated_forbidden_character_is_reported_once() {
+    let errors = SubscriberName::try_from("<<Ursula>>".to_string()).unwrap_err();
+
+    assert_eq!(
+        errors.errors(),
+        [
+            ValidationError::ForbiddenCharacter { character: '<' },
+            ValidationError::ForbiddenCharacter { character: '>' },
+        ]
+    );
+}
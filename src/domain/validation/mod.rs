@@ -0,0 +1,132 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// A validation rule that a value broke, along with the rule's parameters.
+#[derive(thiserror::Error, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum ValidationError {
+    #[error("must not be empty")]
+    Empty,
+    #[error("must be at most {max} characters long")]
+    TooLong { max: usize },
+    #[error("must not contain {character:?}")]
+    ForbiddenCharacter { character: char },
+    #[error("is not a valid email address")]
+    InvalidEmail,
+    #[error("must not use the disposable email domain {domain}")]
+    DisposableDomain { domain: String },
+    #[error("uses the domain {domain}, which does not accept email")]
+    UndeliverableDomain { domain: String },
+}
+
+/// Every rule that a single value broke.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationErrors(Vec<ValidationError>);
+
+impl ValidationErrors {
+    /// Returns `Ok(())` if no rule was broken.
+    pub fn into_result(errors: Vec<ValidationError>) -> Result<(), Self> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Self(errors))
+        }
+    }
+
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.0
+    }
+}
+
+impl From<ValidationError> for ValidationErrors {
+    fn from(error: ValidationError) -> Self {
+        Self(vec![error])
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// A rule broken by one field of a request.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: &'static str,
+    #[serde(flatten)]
+    pub error: ValidationError,
+    pub message: String,
+    /// A corrected value that the client may offer to the user, e.g. "Did you mean ...?".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, error: ValidationError) -> Self {
+        Self {
+            field,
+            message: error.to_string(),
+            error,
+            suggestion: None,
+        }
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+}
+
+/// Collects the errors of every field of a request, so that they are all reported at once.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    /// Returns the field's value if it is valid, and records its errors otherwise.
+    pub fn check<T>(
+        &mut self,
+        field: &'static str,
+        value: Result<T, ValidationErrors>,
+    ) -> Option<T> {
+        match value {
+            Ok(value) => Some(value),
+            Err(errors) => {
+                self.0.extend(
+                    errors
+                        .0
+                        .into_iter()
+                        .map(|error| FieldError::new(field, error)),
+                );
+                None
+            }
+        }
+    }
+
+    /// Attaches a suggestion to every error already recorded for `field`.
+    pub fn suggest(&mut self, field: &'static str, suggestion: Option<String>) {
+        for error in self.0.iter_mut().filter(|error| error.field == field) {
+            error.suggestion.clone_from(&suggestion);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<FieldError> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,79 @@
+use super::*;
+
+#[test]
+fn every_broken_rule_is_described() {
+    let errors = ValidationErrors::into_result(vec![
+        ValidationError::TooLong { max: 3 },
+        ValidationError::ForbiddenCharacter { character: '<' },
+    ])
+    .unwrap_err();
+
+    assert_eq!(
+        errors.to_string(),
+        "must be at most 3 characters long, must not contain '<'"
+    );
+}
+
+#[test]
+fn no_broken_rule_is_a_success() {
+    assert_eq!(ValidationErrors::into_result(vec![]), Ok(()));
+}
+
+#[test]
+fn the_errors_of_every_field_are_collected() {
+    let mut errors = FieldErrors::default();
+
+    let email = errors.check("email", Ok::<_, ValidationErrors>("ursula@example.com"));
+    let name = errors.check::<&str>(
+        "name",
+        Err(ValidationErrors(vec![
+            ValidationError::Empty,
+            ValidationError::TooLong { max: 3 },
+        ])),
+    );
+
+    assert_eq!(email, Some("ursula@example.com"));
+    assert_eq!(name, None);
+    assert_eq!(
+        errors.into_vec(),
+        [
+            FieldError::new("name", ValidationError::Empty),
+            FieldError::new("name", ValidationError::TooLong { max: 3 }),
+        ]
+    );
+}
+
+#[test]
+fn field_errors_serialize_their_rule_and_its_parameters() {
+    let error = FieldError::new(
+        "name",
+        ValidationError::ForbiddenCharacter { character: '<' },
+    );
+
+    assert_eq!(
+        serde_json::to_value(error).unwrap(),
+        serde_json::json!({
+            "field": "name",
+            "rule": "forbidden_character",
+            "character": "<",
+            "message": "must not contain '<'",
+        })
+    );
+}
+
+#[test]
+fn suggestions_are_attached_to_the_errors_of_their_field() {
+    let mut errors = FieldErrors::default();
+    errors.check::<()>("email", Err(ValidationError::InvalidEmail.into()));
+    errors.check::<()>("name", Err(ValidationError::Empty.into()));
+
+    errors.suggest("email", Some("ursula@gmail.com".into()));
+
+    let errors = errors.into_vec();
+    assert_eq!(errors[0].suggestion.as_deref(), Some("ursula@gmail.com"));
+    assert_eq!(errors[1].suggestion, None);
+    assert_eq!(
+        serde_json::to_value(&errors[0]).unwrap()["suggestion"],
+        "ursula@gmail.com"
+    );
+}
@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::proto::op::ResponseCode;
+use hickory_resolver::TokioAsyncResolver;
+
+use crate::domain_verification::{DomainStatus, DomainVerifier};
+
+/// Looks up the domain's MX records, falling back to its address records as mail servers do
+/// (RFC 5321, section 5.1).
+#[derive(Debug)]
+pub struct DnsDomainVerifier {
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsDomainVerifier {
+    /// Uses the system's resolver configuration, e.g. `/etc/resolv.conf`.
+    pub fn from_system_conf() -> Result<Self> {
+        let (config, options) = hickory_resolver::system_conf::read_system_conf()
+            .context("Failed to read the system's DNS resolver configuration")?;
+        Ok(Self {
+            resolver: TokioAsyncResolver::tokio(config, options),
+        })
+    }
+}
+
+#[async_trait]
+impl DomainVerifier for DnsDomainVerifier {
+    #[tracing::instrument(skip(self))]
+    async fn verify(&self, domain: &str) -> Result<DomainStatus> {
+        // The trailing dot keeps the resolver from appending its search domains.
+        let fqdn = format!("{}.", domain);
+
+        match self.resolver.mx_lookup(fqdn.as_str()).await {
+            // A single MX record pointing at the root is a "null MX": the domain takes no mail
+            // (RFC 7505).
+            Ok(mx) => {
+                let null_mx = mx.iter().all(|record| record.exchange().is_root());
+                Ok(if null_mx {
+                    DomainStatus::Undeliverable
+                } else {
+                    DomainStatus::Deliverable
+                })
+            }
+            Err(e) if does_not_exist(&e) => Ok(DomainStatus::Undeliverable),
+            Err(e) if has_no_records(&e) => match self.resolver.lookup_ip(fqdn.as_str()).await {
+                Ok(_) => Ok(DomainStatus::Deliverable),
+                Err(e) if has_no_records(&e) => Ok(DomainStatus::Undeliverable),
+                Err(e) => Err(e).context("Failed to look up the domain's address records"),
+            },
+            Err(e) => Err(e).context("Failed to look up the domain's MX records"),
+        }
+    }
+}
+
+fn has_no_records(e: &ResolveError) -> bool {
+    matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. })
+}
+
+fn does_not_exist(e: &ResolveError) -> bool {
+    matches!(
+        e.kind(),
+        ResolveErrorKind::NoRecordsFound {
+            response_code: ResponseCode::NXDomain,
+            ..
+        }
+    )
+}
@@ -0,0 +1,31 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::domain_verification::{DomainStatus, DomainVerifier};
+
+/// Treats every domain as deliverable except a fixed list, for tests and local development.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryDomainVerifier {
+    undeliverable_domains: HashSet<String>,
+}
+
+impl InMemoryDomainVerifier {
+    pub fn new(undeliverable_domains: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            undeliverable_domains: undeliverable_domains.into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl DomainVerifier for InMemoryDomainVerifier {
+    async fn verify(&self, domain: &str) -> Result<DomainStatus> {
+        Ok(if self.undeliverable_domains.contains(domain) {
+            DomainStatus::Undeliverable
+        } else {
+            DomainStatus::Deliverable
+        })
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::warn;
+
+pub use dns::*;
+pub use in_memory::*;
+
+mod dns;
+mod in_memory;
+
+/// How often the cache drops expired entries.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DomainStatus {
+    /// The domain advertises somewhere to deliver mail to.
+    Deliverable,
+    /// The domain does not exist or explicitly refuses mail.
+    Undeliverable,
+}
+
+/// Tells whether a domain can receive email. Errors mean the answer is unknown, e.g. because
+/// the lookup timed out.
+#[async_trait]
+pub trait DomainVerifier: std::fmt::Debug + Send + Sync {
+    async fn verify(&self, domain: &str) -> Result<DomainStatus>;
+}
+
+/// What to do with a subscription when its domain cannot be verified.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Accept the subscription: the confirmation email will tell.
+    #[default]
+    Open,
+    /// Refuse the subscription until the domain can be verified.
+    Closed,
+}
+
+/// Verifies domains through a [`DomainVerifier`], caching its answers.
+#[derive(Clone, Debug)]
+pub struct DomainVerification {
+    verifier: Arc<dyn DomainVerifier>,
+    cache: Arc<Mutex<Cache>>,
+    cache_ttl: Duration,
+    timeout: Duration,
+    failure_policy: FailurePolicy,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    entries: HashMap<String, (DomainStatus, Instant)>,
+    last_pruned: Option<Instant>,
+}
+
+impl DomainVerification {
+    pub fn new(
+        verifier: impl DomainVerifier + 'static,
+        cache_ttl: Duration,
+        timeout: Duration,
+        failure_policy: FailurePolicy,
+    ) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+            cache: Default::default(),
+            cache_ttl,
+            timeout,
+            failure_policy,
+        }
+    }
+
+    /// Only fails when the domain cannot be verified and the policy is [`FailurePolicy::Closed`].
+    /// Failed lookups are not cached.
+    #[tracing::instrument(skip(self))]
+    pub async fn verify(&self, domain: &str) -> Result<DomainStatus> {
+        if let Some(status) = self.cached(domain, Instant::now()) {
+            return Ok(status);
+        }
+
+        let outcome = match tokio::time::timeout(self.timeout, self.verifier.verify(domain)).await {
+            Ok(outcome) => outcome,
+            Err(_) => Err(anyhow!("Timed out after {}ms", self.timeout.as_millis())),
+        };
+
+        match outcome {
+            Ok(status) => {
+                self.store(domain, status, Instant::now());
+                Ok(status)
+            }
+            Err(e) => match self.failure_policy {
+                FailurePolicy::Open => {
+                    warn!(error = ?e, "Failed to verify an email domain. Accepting it");
+                    Ok(DomainStatus::Deliverable)
+                }
+                FailurePolicy::Closed => {
+                    Err(e.context(format!("Failed to verify the email domain {}", domain)))
+                }
+            },
+        }
+    }
+
+    fn cached(&self, domain: &str, now: Instant) -> Option<DomainStatus> {
+        let cache = self.cache.lock().unwrap();
+        match cache.entries.get(domain) {
+            Some(&(status, expires_at)) if expires_at > now => Some(status),
+            _ => None,
+        }
+    }
+
+    fn store(&self, domain: &str, status: DomainStatus, now: Instant) {
+        let mut cache = self.cache.lock().unwrap();
+
+        if cache
+            .last_pruned
+            .is_none_or(|t| now.saturating_duration_since(t) >= PRUNE_INTERVAL)
+        {
+            cache.entries.retain(|_, (_, expires_at)| *expires_at > now);
+            cache.last_pruned = Some(now);
+        }
+
+        cache
+            .entries
+            .insert(domain.to_owned(), (status, now + self.cache_ttl));
+    }
+}
+
+#[cfg(test)]
+mod tests;
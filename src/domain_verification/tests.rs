@@ -0,0 +1,128 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::bail;
+use claims::assert_err;
+
+use super::*;
+
+const TTL: Duration = Duration::from_secs(60);
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Counts lookups, answering with `status`, or failing if there is none.
+#[derive(Debug, Default, Clone)]
+struct CountingVerifier {
+    status: Option<DomainStatus>,
+    delay: Duration,
+    lookups: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl DomainVerifier for CountingVerifier {
+    async fn verify(&self, _domain: &str) -> Result<DomainStatus> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        match self.status {
+            Some(status) => Ok(status),
+            None => bail!("SERVFAIL"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn answers_are_cached() -> Result<()> {
+    let verifier = CountingVerifier {
+        status: Some(DomainStatus::Undeliverable),
+        ..Default::default()
+    };
+    let verification = DomainVerification::new(verifier.clone(), TTL, TIMEOUT, FailurePolicy::Open);
+
+    for _ in 0..3 {
+        assert_eq!(
+            verification.verify("gmial.com").await?,
+            DomainStatus::Undeliverable
+        );
+    }
+    assert_eq!(verifier.lookups.load(Ordering::SeqCst), 1);
+
+    verification.verify("gmail.com").await?;
+    assert_eq!(verifier.lookups.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[test]
+fn cached_answers_expire_after_the_ttl() {
+    let verification = DomainVerification::new(
+        InMemoryDomainVerifier::default(),
+        TTL,
+        TIMEOUT,
+        FailurePolicy::Open,
+    );
+    let now = Instant::now();
+
+    verification.store("gmail.com", DomainStatus::Deliverable, now);
+
+    assert_eq!(
+        verification.cached("gmail.com", now + TTL - Duration::from_secs(1)),
+        Some(DomainStatus::Deliverable)
+    );
+    assert_eq!(verification.cached("gmail.com", now + TTL), None);
+}
+
+#[tokio::test]
+async fn failed_lookups_are_accepted_when_failing_open() -> Result<()> {
+    let verifier = CountingVerifier::default();
+    let verification = DomainVerification::new(verifier.clone(), TTL, TIMEOUT, FailurePolicy::Open);
+
+    assert_eq!(
+        verification.verify("gmail.com").await?,
+        DomainStatus::Deliverable
+    );
+    // The failure is not cached: the next subscription tries again.
+    verification.verify("gmail.com").await?;
+    assert_eq!(verifier.lookups.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_lookups_are_errors_when_failing_closed() {
+    let verification = DomainVerification::new(
+        CountingVerifier::default(),
+        TTL,
+        TIMEOUT,
+        FailurePolicy::Closed,
+    );
+
+    assert_err!(verification.verify("gmail.com").await);
+}
+
+#[tokio::test]
+async fn slow_lookups_time_out() {
+    let verifier = CountingVerifier {
+        status: Some(DomainStatus::Deliverable),
+        delay: TIMEOUT * 10,
+        ..Default::default()
+    };
+    let verification = DomainVerification::new(verifier, TTL, TIMEOUT, FailurePolicy::Closed);
+
+    let e = verification.verify("gmail.com").await.unwrap_err();
+
+    assert!(format!("{:#}", e).contains("Timed out after 100ms"));
+}
+
+#[tokio::test]
+async fn the_in_memory_verifier_only_rejects_its_listed_domains() -> Result<()> {
+    let verifier = InMemoryDomainVerifier::new(["gmial.com".to_string()]);
+
+    assert_eq!(
+        verifier.verify("gmial.com").await?,
+        DomainStatus::Undeliverable
+    );
+    assert_eq!(
+        verifier.verify("gmail.com").await?,
+        DomainStatus::Deliverable
+    );
+
+    Ok(())
+}
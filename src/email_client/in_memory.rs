@@ -0,0 +1,58 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::email_client::{EmailMessage, EmailTransport};
+
+/// Keeps every email it is asked to send, for tests and local development.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryTransport {
+    sent_emails: Arc<Mutex<Vec<SentEmail>>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SentEmail {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl InMemoryTransport {
+    pub fn sent_emails(&self) -> Vec<SentEmail> {
+        self.sent_emails.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl EmailTransport for InMemoryTransport {
+    fn provider(&self) -> &'static str {
+        "in_memory"
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<()> {
+        let email = SentEmail {
+            from: message.from.to_owned(),
+            to: message.to.to_owned(),
+            subject: message.subject.to_owned(),
+            html_body: message.html_body.to_owned(),
+            text_body: message.text_body.to_owned(),
+            headers: message
+                .headers
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+        };
+        self.sent_emails.lock().unwrap().push(email);
+
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+}
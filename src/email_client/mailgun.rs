@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::multipart::Form;
+use reqwest::{Client, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::email_client::{EmailErrorCategory, EmailMessage, EmailProviderError, EmailTransport};
+use crate::telemetry::trace_context_headers;
+
+/// Sends emails through the Mailgun messages API.
+#[derive(Debug)]
+pub struct MailgunTransport {
+    http_client: Client,
+    base_url: String,
+    domain: String,
+    api_key: Secret<String>,
+}
+
+impl MailgunTransport {
+    pub fn new(
+        base_url: String,
+        domain: String,
+        api_key: Secret<String>,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let http_client = Client::builder().timeout(timeout).build()?;
+
+        Ok(Self {
+            http_client,
+            base_url,
+            domain,
+            api_key,
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for MailgunTransport {
+    fn provider(&self) -> &'static str {
+        "mailgun"
+    }
+
+    #[tracing::instrument(skip_all, fields(self.base_url, self.domain))]
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<()> {
+        let url = format!("{}/v3/{}/messages", self.base_url, self.domain);
+        let mut form = Form::new()
+            .text("from", message.from.to_owned())
+            .text("to", message.to.to_owned())
+            .text("subject", message.subject.to_owned())
+            .text("text", message.text_body.to_owned())
+            .text("html", message.html_body.to_owned());
+        for &(name, value) in message.headers {
+            form = form.text(format!("h:{}", name), value.to_owned());
+        }
+
+        let response = self
+            .http_client
+            .post(url)
+            .headers(trace_context_headers())
+            .basic_auth("api", Some(self.api_key.expose_secret()))
+            .multipart(form)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        // Mailgun answers with `{"message": "..."}`, or plain text for some authentication errors.
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|body| body["message"].as_str().map(str::to_owned))
+            .unwrap_or(body);
+
+        Err(EmailProviderError {
+            provider: self.provider(),
+            status,
+            category: categorize(status, &message),
+            message: format!("{} {}", status, message).trim().to_owned(),
+        }
+        .into())
+    }
+
+    /// Fetches the sending domain.
+    #[tracing::instrument(skip_all, fields(self.base_url, self.domain))]
+    async fn ping(&self) -> Result<()> {
+        self.http_client
+            .get(format!("{}/v3/domains/{}", self.base_url, self.domain))
+            .headers(trace_context_headers())
+            .basic_auth("api", Some(self.api_key.expose_secret()))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+fn categorize(status: StatusCode, message: &str) -> EmailErrorCategory {
+    let message = message.to_lowercase();
+    match status {
+        StatusCode::BAD_REQUEST
+            if message.contains("'to' parameter")
+                || message.contains("to parameter")
+                || message.contains("recipient") =>
+        {
+            EmailErrorCategory::RejectedRecipient
+        }
+        _ => EmailErrorCategory::from_status(status),
+    }
+}
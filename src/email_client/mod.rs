@@ -1,35 +1,60 @@
+use std::sync::Arc;
+use std::time::Instant;
+
 use anyhow::Result;
-use reqwest::Client;
-use secrecy::{ExposeSecret, Secret};
-use serde::Serialize;
-use std::time::Duration;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+
+use crate::metrics::record_email_send;
+
+pub use in_memory::*;
+pub use mailgun::*;
+pub use postmark::*;
+pub use ses::*;
+pub use smtp::*;
 
+mod in_memory;
+mod mailgun;
+mod postmark;
+mod ses;
+mod smtp;
+
+/// An outgoing email, as handed to an [`EmailTransport`].
 #[derive(Debug)]
+pub struct EmailMessage<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    pub subject: &'a str,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+    pub headers: &'a [(&'a str, &'a str)],
+}
+
+#[async_trait]
+pub trait EmailTransport: std::fmt::Debug + Send + Sync {
+    /// Names the provider in metrics.
+    fn provider(&self) -> &'static str;
+
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<()>;
+
+    /// Checks that the provider is reachable and accepts our credentials, without sending anything.
+    async fn ping(&self) -> Result<()>;
+}
+
+#[derive(Clone, Debug)]
 pub struct EmailClient {
-    http_client: Client,
-    base_url: String,
     sender: String,
-    authorization_token: Secret<String>,
+    transport: Arc<dyn EmailTransport>,
 }
 
 impl EmailClient {
-    pub fn new(
-        url: String,
-        sender: String,
-        authorization_token: Secret<String>,
-        timeout: Duration,
-    ) -> Self {
-        let http_client = Client::builder().timeout(timeout).build().unwrap();
-
+    pub fn new(sender: String, transport: impl EmailTransport + 'static) -> Self {
         Self {
-            http_client,
-            base_url: url,
             sender,
-            authorization_token,
+            transport: Arc::new(transport),
         }
     }
 
-    #[tracing::instrument(skip(self), fields(self.base_url, self.sender))]
     pub async fn send_email(
         &self,
         recipient: &str,
@@ -37,38 +62,126 @@ impl EmailClient {
         html_content: &str,
         text_content: &str,
     ) -> Result<()> {
-        let url = format!("{}/email", self.base_url);
-        let request_body = SendEmailRequest {
-            from: &self.sender,
-            to: recipient,
-            subject,
-            html_body: html_content,
-            text_body: text_content,
-        };
+        self.send_email_with_headers(recipient, subject, html_content, text_content, &[])
+            .await
+    }
+
+    #[tracing::instrument(skip(self), fields(self.sender))]
+    pub async fn send_email_with_headers(
+        &self,
+        recipient: &str,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<()> {
+        let start = Instant::now();
+        let outcome = self
+            .transport
+            .send(&EmailMessage {
+                from: &self.sender,
+                to: recipient,
+                subject,
+                html_body: html_content,
+                text_body: text_content,
+                headers,
+            })
+            .await;
+
+        record_email_send(
+            self.transport.provider(),
+            start.elapsed(),
+            outcome.as_ref().err().map(response_code).as_deref(),
+        );
+        outcome
+    }
 
-        self.http_client
-            .post(url)
-            .header(
-                "X-Postmark-Server-Token",
-                self.authorization_token.expose_secret(),
-            )
-            .json(&request_body)
-            .send()
-            .await?
-            .error_for_status()?;
-
-        Ok(())
+    pub fn provider(&self) -> &'static str {
+        self.transport.provider()
     }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn ping(&self) -> Result<()> {
+        self.transport.ping().await
+    }
+}
+
+/// Provider-agnostic reasons for an email to be refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmailErrorCategory {
+    Throttled,
+    RejectedRecipient,
+    AuthFailure,
+    ProviderUnavailable,
+    InvalidRequest,
+}
+
+impl EmailErrorCategory {
+    pub fn is_transient(self) -> bool {
+        matches!(
+            self,
+            EmailErrorCategory::Throttled | EmailErrorCategory::ProviderUnavailable
+        )
+    }
+
+    /// The category implied by the HTTP status alone, for errors a provider does not name.
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => EmailErrorCategory::Throttled,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => EmailErrorCategory::AuthFailure,
+            status if status.is_server_error() => EmailErrorCategory::ProviderUnavailable,
+            _ => EmailErrorCategory::InvalidRequest,
+        }
+    }
+}
+
+/// An error response from an email provider, mapped onto an [`EmailErrorCategory`].
+#[derive(thiserror::Error, Debug)]
+#[error("{provider} refused the email ({category:?}): {message}")]
+pub struct EmailProviderError {
+    pub provider: &'static str,
+    pub status: StatusCode,
+    pub category: EmailErrorCategory,
+    pub message: String,
+}
+
+/// The status the provider answered a failed send with, or `none` if it never answered.
+pub fn response_code(error: &anyhow::Error) -> String {
+    let code = if let Some(error) = error.downcast_ref::<EmailProviderError>() {
+        Some(error.status.as_u16().to_string())
+    } else if let Some(error) = error.downcast_ref::<lettre::transport::smtp::Error>() {
+        error.status().map(|code| code.to_string())
+    } else if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        error.status().map(|status| status.as_u16().to_string())
+    } else {
+        None
+    };
+
+    code.unwrap_or_else(|| "none".to_string())
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "PascalCase")]
-struct SendEmailRequest<'a> {
-    from: &'a str,
-    to: &'a str,
-    subject: &'a str,
-    html_body: &'a str,
-    text_body: &'a str,
+/// Whether a failed send is worth retrying.
+///
+/// Errors that aren't recognised, e.g. an address or message that could not be built, are
+/// permanent: retrying them would fail in the same way.
+pub fn is_transient_error(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<EmailProviderError>() {
+        return error.category.is_transient();
+    }
+
+    if let Some(error) = error.downcast_ref::<lettre::transport::smtp::Error>() {
+        return !error.is_permanent();
+    }
+
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return match error.status() {
+            Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+            // No answer came back, unless the request could not even be built.
+            None => !error.is_builder(),
+        };
+    }
+
+    false
 }
 
 #[cfg(test)]
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+
+use crate::email_client::{EmailMessage, EmailTransport};
+use crate::telemetry::trace_context_headers;
+
+/// Sends emails through the Postmark HTTP API.
+#[derive(Debug)]
+pub struct PostmarkTransport {
+    http_client: Client,
+    base_url: String,
+    authorization_token: Secret<String>,
+}
+
+impl PostmarkTransport {
+    pub fn new(base_url: String, authorization_token: Secret<String>, timeout: Duration) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+
+        Self {
+            http_client,
+            base_url,
+            authorization_token,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for PostmarkTransport {
+    fn provider(&self) -> &'static str {
+        "postmark"
+    }
+
+    #[tracing::instrument(skip_all, fields(self.base_url))]
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<()> {
+        let url = format!("{}/email", self.base_url);
+        let request_body = SendEmailRequest {
+            from: message.from,
+            to: message.to,
+            subject: message.subject,
+            html_body: message.html_body,
+            text_body: message.text_body,
+            headers: message
+                .headers
+                .iter()
+                .map(|&(name, value)| EmailHeader { name, value })
+                .collect(),
+        };
+
+        self.http_client
+            .post(url)
+            .headers(trace_context_headers())
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Fetches the server the token belongs to.
+    #[tracing::instrument(skip_all, fields(self.base_url))]
+    async fn ping(&self) -> Result<()> {
+        self.http_client
+            .get(format!("{}/server", self.base_url))
+            .headers(trace_context_headers())
+            .header("Accept", "application/json")
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<EmailHeader<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct EmailHeader<'a> {
+    name: &'a str,
+    value: &'a str,
+}
@@ -0,0 +1,311 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, RequestBuilder, Url};
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::email_client::{EmailErrorCategory, EmailMessage, EmailProviderError, EmailTransport};
+use crate::telemetry::trace_context_headers;
+
+/// Sends emails through the Amazon SES v2 `SendEmail` API.
+#[derive(Debug)]
+pub struct SesTransport {
+    http_client: Client,
+    base_url: Url,
+    region: String,
+    access_key_id: String,
+    secret_access_key: Secret<String>,
+}
+
+impl SesTransport {
+    /// `base_url` defaults to the regional SES endpoint.
+    pub fn new(
+        region: String,
+        access_key_id: String,
+        secret_access_key: Secret<String>,
+        base_url: Option<String>,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let http_client = Client::builder().timeout(timeout).build()?;
+        let base_url =
+            base_url.unwrap_or_else(|| format!("https://email.{}.amazonaws.com", region));
+        let base_url = Url::parse(&base_url).context("Invalid SES base URL")?;
+
+        Ok(Self {
+            http_client,
+            base_url,
+            region,
+            access_key_id,
+            secret_access_key,
+        })
+    }
+
+    /// Builds a request to the SES API signed with Signature Version 4.
+    fn signed_request(&self, method: Method, url: Url, body: Vec<u8>) -> RequestBuilder {
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_owned(),
+        };
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let authorization = sigv4_authorization(
+            method.as_str(),
+            url.path(),
+            &[
+                ("content-type", "application/json"),
+                ("host", &host),
+                ("x-amz-date", &amz_date),
+            ],
+            &body,
+            &SigningParams {
+                access_key_id: &self.access_key_id,
+                secret_access_key: self.secret_access_key.expose_secret(),
+                region: &self.region,
+                service: "ses",
+                datetime: now,
+            },
+        );
+
+        self.http_client
+            .request(method, url)
+            .headers(trace_context_headers())
+            .header("Content-Type", "application/json")
+            .header("X-Amz-Date", amz_date)
+            .header("Authorization", authorization)
+            .body(body)
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SesTransport {
+    fn provider(&self) -> &'static str {
+        "ses"
+    }
+
+    #[tracing::instrument(skip_all, fields(self.base_url = %self.base_url))]
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<()> {
+        let url = self.base_url.join("/v2/email/outbound-emails")?;
+        let body = serde_json::to_vec(&SendEmailRequest::from(message))?;
+
+        let response = self.signed_request(Method::POST, url, body).send().await?;
+
+        if response.status().is_success() {
+            return Ok(());
+        }
+
+        let status = response.status();
+        let error_type = response
+            .headers()
+            .get("x-amzn-ErrorType")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        // The header reads `Name:uri`; the body's `__type` may be `namespace#Name`.
+        let error_type = error_type
+            .or_else(|| body["__type"].as_str().map(str::to_owned))
+            .map(|t| {
+                let t = t.split(':').next().unwrap_or_default();
+                t.rsplit('#').next().unwrap_or_default().to_owned()
+            })
+            .unwrap_or_default();
+        let message = body["message"]
+            .as_str()
+            .or_else(|| body["Message"].as_str())
+            .unwrap_or_default();
+
+        Err(EmailProviderError {
+            provider: self.provider(),
+            status,
+            category: match error_type.as_str() {
+                "TooManyRequestsException" | "LimitExceededException" | "ThrottlingException" => {
+                    EmailErrorCategory::Throttled
+                }
+                "MessageRejected" => EmailErrorCategory::RejectedRecipient,
+                "UnrecognizedClientException"
+                | "InvalidSignatureException"
+                | "SignatureDoesNotMatch"
+                | "AccessDeniedException"
+                | "MissingAuthenticationTokenException"
+                | "ExpiredTokenException" => EmailErrorCategory::AuthFailure,
+                _ => EmailErrorCategory::from_status(status),
+            },
+            message: format!("{} {} {}", status, error_type, message)
+                .trim()
+                .to_owned(),
+        }
+        .into())
+    }
+
+    /// Fetches the account's sending status.
+    #[tracing::instrument(skip_all, fields(self.base_url = %self.base_url))]
+    async fn ping(&self) -> Result<()> {
+        let url = self.base_url.join("/v2/email/account")?;
+        self.signed_request(Method::GET, url, Vec::new())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from_email_address: &'a str,
+    destination: Destination<'a>,
+    content: Content<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Destination<'a> {
+    to_addresses: [&'a str; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Content<'a> {
+    simple: SimpleContent<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SimpleContent<'a> {
+    subject: ContentData<'a>,
+    body: Body<'a>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<EmailHeader<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Body<'a> {
+    html: ContentData<'a>,
+    text: ContentData<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContentData<'a> {
+    data: &'a str,
+    charset: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct EmailHeader<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl<'a> From<&'a EmailMessage<'a>> for SendEmailRequest<'a> {
+    fn from(message: &'a EmailMessage<'a>) -> Self {
+        let data = |data| ContentData {
+            data,
+            charset: "UTF-8",
+        };
+
+        Self {
+            from_email_address: message.from,
+            destination: Destination {
+                to_addresses: [message.to],
+            },
+            content: Content {
+                simple: SimpleContent {
+                    subject: data(message.subject),
+                    body: Body {
+                        html: data(message.html_body),
+                        text: data(message.text_body),
+                    },
+                    headers: message
+                        .headers
+                        .iter()
+                        .map(|&(name, value)| EmailHeader { name, value })
+                        .collect(),
+                },
+            },
+        }
+    }
+}
+
+pub(super) struct SigningParams<'a> {
+    pub access_key_id: &'a str,
+    pub secret_access_key: &'a str,
+    pub region: &'a str,
+    pub service: &'a str,
+    pub datetime: DateTime<Utc>,
+}
+
+/// Computes an AWS Signature Version 4 `Authorization` header for a request without a query string.
+///
+/// `headers` must be lowercase and sorted by name, and include `host` and `x-amz-date`.
+pub(super) fn sigv4_authorization(
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    payload: &[u8],
+    params: &SigningParams<'_>,
+) -> String {
+    let date = params.datetime.format("%Y%m%d").to_string();
+    let amz_date = params.datetime.format("%Y%m%dT%H%M%SZ").to_string();
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        path,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(payload))
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, params.region, params.service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let signing_key = [params.region, params.service, "aws4_request"].iter().fold(
+        hmac_sha256(
+            format!("AWS4{}", params.secret_access_key).as_bytes(),
+            date.as_bytes(),
+        ),
+        |key, part| hmac_sha256(&key, part.as_bytes()),
+    );
+    let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        params.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{:02x}", byte).unwrap();
+        hex
+    })
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::MultiPart;
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+
+use crate::configuration::SmtpSettings;
+use crate::email_client::{EmailMessage, EmailTransport};
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Connect in plain text on the submission port (587), then upgrade with `STARTTLS`.
+    #[default]
+    Starttls,
+    /// Connect over TLS from the start on the submissions port (465).
+    Implicit,
+    /// Never encrypt the connection. Only meant for local development servers.
+    None,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpAuthMechanism {
+    Plain,
+    Login,
+}
+
+impl From<SmtpAuthMechanism> for Mechanism {
+    fn from(value: SmtpAuthMechanism) -> Self {
+        match value {
+            SmtpAuthMechanism::Plain => Mechanism::Plain,
+            SmtpAuthMechanism::Login => Mechanism::Login,
+        }
+    }
+}
+
+/// Sends emails through an SMTP relay.
+#[derive(Debug)]
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(settings: &SmtpSettings, timeout: Duration) -> Result<Self> {
+        let mut builder = match settings.tls {
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+            }
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?,
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            }
+        };
+
+        if let Some(port) = settings.port {
+            builder = builder.port(port);
+        }
+
+        if let Some(username) = &settings.username {
+            let password = settings
+                .password
+                .as_ref()
+                .context("An SMTP password is required along with the username")?;
+            builder = builder
+                .credentials(Credentials::new(
+                    username.clone(),
+                    password.expose_secret().clone(),
+                ))
+                .authentication(
+                    settings
+                        .auth_mechanisms
+                        .iter()
+                        .copied()
+                        .map(Mechanism::from)
+                        .collect(),
+                );
+        }
+
+        Ok(Self {
+            mailer: builder.timeout(Some(timeout)).build(),
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpTransport {
+    fn provider(&self) -> &'static str {
+        "smtp"
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<()> {
+        let mut email = Message::builder()
+            .from(message.from.parse().context("Invalid sender address")?)
+            .to(message.to.parse().context("Invalid recipient address")?)
+            .subject(message.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                message.text_body.to_owned(),
+                message.html_body.to_owned(),
+            ))
+            .context("Failed to build the email")?;
+
+        for &(name, value) in message.headers {
+            let name = HeaderName::new_from_ascii(name.to_owned())
+                .with_context(|| format!("Invalid header name: {}", name))?;
+            email
+                .headers_mut()
+                .insert_raw(HeaderValue::new(name, value.to_owned()));
+        }
+
+        self.mailer.send(email).await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn ping(&self) -> Result<()> {
+        if !self.mailer.test_connection().await? {
+            anyhow::bail!("The SMTP relay did not answer NOOP");
+        }
+
+        Ok(())
+    }
+}
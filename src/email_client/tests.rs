@@ -1,15 +1,27 @@
 use anyhow::Result;
+use chrono::{TimeZone, Utc};
 use claims::assert_err;
+use config::{Config, FileFormat};
 use fake::faker::internet::en;
 use fake::faker::lorem;
 use fake::{Fake, Faker};
 use secrecy::Secret;
 use serde_json::Value;
 use std::time::Duration;
-use wiremock::matchers::{any, header, header_exists, method, path};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use wiremock::matchers::{
+    any, body_partial_json, header, header_exists, header_regex, method, path,
+};
 use wiremock::{Match, Mock, MockServer, Request, ResponseTemplate};
 
-use crate::email_client::EmailClient;
+use crate::configuration::{EmailClientSettings, SmtpSettings};
+use crate::email_client::ses::{sigv4_authorization, SigningParams};
+use crate::email_client::{
+    is_transient_error, response_code, EmailClient, EmailErrorCategory, EmailProviderError,
+    InMemoryTransport, MailgunTransport, PostmarkTransport, SentEmail, SesTransport,
+    SmtpAuthMechanism, SmtpTls, SmtpTransport,
+};
 
 #[tokio::test]
 async fn send_email_sends_the_expected_request() -> Result<()> {
@@ -33,6 +45,34 @@ async fn send_email_sends_the_expected_request() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn send_email_with_headers_forwards_the_custom_headers() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    let email_client = email_client(mock_server.uri());
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "Headers": [{ "Name": "List-Unsubscribe", "Value": "<https://example.com>" }]
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    email_client
+        .send_email_with_headers(
+            &email(),
+            &subject(),
+            &content(),
+            &content(),
+            &[("List-Unsubscribe", "<https://example.com>")],
+        )
+        .await?;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn send_email_succeeds_if_the_server_returns_200() -> Result<()> {
     let mock_server = MockServer::start().await;
@@ -90,6 +130,614 @@ async fn send_email_times_out_if_the_server_takes_too_long() {
     assert_err!(outcome);
 }
 
+#[tokio::test]
+async fn server_errors_are_transient() -> Result<()> {
+    for status in [500, 503, 429] {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(status))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let error = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await
+            .unwrap_err();
+
+        assert!(is_transient_error(&error), "{} should be transient", status);
+        assert_eq!(response_code(&error), status.to_string());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_errors_are_permanent() -> Result<()> {
+    for status in [400, 401, 422] {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(status))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let error = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await
+            .unwrap_err();
+
+        assert!(
+            !is_transient_error(&error),
+            "{} should be permanent",
+            status
+        );
+        assert_eq!(response_code(&error), status.to_string());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn timeouts_are_transient() {
+    let mock_server = MockServer::start().await;
+    let email_client = email_client(mock_server.uri());
+
+    let response = ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
+    Mock::given(any())
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let error = email_client
+        .send_email(&email(), &subject(), &content(), &content())
+        .await
+        .unwrap_err();
+
+    assert!(is_transient_error(&error));
+    assert_eq!(response_code(&error), "none");
+}
+
+#[test]
+fn unrecognised_errors_are_permanent() {
+    let error = anyhow::Error::new("not an address".parse::<lettre::Address>().unwrap_err())
+        .context("Invalid recipient address");
+    assert!(!is_transient_error(&error));
+
+    let error = anyhow::anyhow!("Failed to build the email");
+    assert!(!is_transient_error(&error));
+}
+
+#[tokio::test]
+async fn the_in_memory_transport_keeps_sent_emails() -> Result<()> {
+    let transport = InMemoryTransport::default();
+    let sender = email();
+    let email_client = EmailClient::new(sender.clone(), transport.clone());
+    let (recipient, subject, html, text) = (email(), subject(), content(), content());
+
+    email_client
+        .send_email_with_headers(&recipient, &subject, &html, &text, &[("X-Test", "1")])
+        .await?;
+
+    assert_eq!(
+        transport.sent_emails(),
+        vec![SentEmail {
+            from: sender,
+            to: recipient,
+            subject,
+            html_body: html,
+            text_body: text,
+            headers: vec![("X-Test".into(), "1".into())],
+        }]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_smtp_transport_authenticates_with_auth_plain() -> Result<()> {
+    let server = FakeSmtpServer::start(250).await?;
+    let email_client = smtp_email_client(&server, SmtpAuthMechanism::Plain)?;
+
+    email_client
+        .send_email_with_headers(
+            "ursula@example.com",
+            "Hello",
+            "<p>Hi</p>",
+            "Hi",
+            &[("List-Unsubscribe-Post", "List-Unsubscribe=One-Click")],
+        )
+        .await?;
+
+    let transcript = server.transcript().await?;
+    // "\0user\0password", base64-encoded.
+    assert!(transcript.contains("AUTH PLAIN AHVzZXIAcGFzc3dvcmQ="));
+    assert!(transcript.contains("MAIL FROM:<newsletter@example.com>"));
+    assert!(transcript.contains("RCPT TO:<ursula@example.com>"));
+    assert!(transcript.contains("Subject: Hello"));
+    assert!(transcript.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_smtp_transport_authenticates_with_auth_login() -> Result<()> {
+    let server = FakeSmtpServer::start(250).await?;
+    let email_client = smtp_email_client(&server, SmtpAuthMechanism::Login)?;
+
+    email_client
+        .send_email("ursula@example.com", "Hello", "<p>Hi</p>", "Hi")
+        .await?;
+
+    let transcript = server.transcript().await?;
+    assert!(transcript.contains("AUTH LOGIN"));
+    // The username and password, base64-encoded, in answer to the server's challenges.
+    assert!(transcript.contains("dXNlcg=="));
+    assert!(transcript.contains("cGFzc3dvcmQ="));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn smtp_errors_are_classified_by_their_reply_code() -> Result<()> {
+    for (code, transient) in [(451, true), (550, false)] {
+        let server = FakeSmtpServer::start(code).await?;
+        let email_client = smtp_email_client(&server, SmtpAuthMechanism::Plain)?;
+
+        let error = email_client
+            .send_email("ursula@example.com", "Hello", "<p>Hi</p>", "Hi")
+            .await
+            .unwrap_err();
+
+        assert_eq!(is_transient_error(&error), transient, "reply code {}", code);
+        assert_eq!(response_code(&error), code.to_string());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sigv4_matches_the_aws_test_suite() {
+    // The `get-vanilla` case from the AWS Signature Version 4 test suite.
+    let authorization = sigv4_authorization(
+        "GET",
+        "/",
+        &[
+            ("host", "example.amazonaws.com"),
+            ("x-amz-date", "20150830T123600Z"),
+        ],
+        b"",
+        &SigningParams {
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            region: "us-east-1",
+            service: "service",
+            datetime: Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap(),
+        },
+    );
+
+    assert_eq!(
+        authorization,
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+        SignedHeaders=host;x-amz-date, \
+        Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    );
+}
+
+#[tokio::test]
+async fn ses_sends_a_signed_send_email_request() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    let email_client = ses_email_client(&mock_server)?;
+
+    Mock::given(path("/v2/email/outbound-emails"))
+        .and(method("POST"))
+        .and(SesSignatureMatcher)
+        .and(body_partial_json(serde_json::json!({
+            "FromEmailAddress": "newsletter@example.com",
+            "Destination": { "ToAddresses": ["ursula@example.com"] },
+            "Content": {
+                "Simple": {
+                    "Subject": { "Data": "Hello" },
+                    "Body": { "Html": { "Data": "<p>Hi</p>" }, "Text": { "Data": "Hi" } },
+                    "Headers": [{ "Name": "X-Test", "Value": "1" }],
+                }
+            }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "MessageId": "1"
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    email_client
+        .send_email_with_headers(
+            "ursula@example.com",
+            "Hello",
+            "<p>Hi</p>",
+            "Hi",
+            &[("X-Test", "1")],
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ses_errors_are_mapped_onto_common_categories() -> Result<()> {
+    let test_cases = [
+        (
+            429,
+            "TooManyRequestsException",
+            EmailErrorCategory::Throttled,
+        ),
+        (
+            400,
+            "MessageRejected",
+            EmailErrorCategory::RejectedRecipient,
+        ),
+        (
+            403,
+            "InvalidSignatureException",
+            EmailErrorCategory::AuthFailure,
+        ),
+        (
+            500,
+            "InternalFailure",
+            EmailErrorCategory::ProviderUnavailable,
+        ),
+        (
+            400,
+            "BadRequestException",
+            EmailErrorCategory::InvalidRequest,
+        ),
+    ];
+
+    for (status, error_type, category) in test_cases {
+        let mock_server = MockServer::start().await;
+        let email_client = ses_email_client(&mock_server)?;
+
+        Mock::given(any())
+            .respond_with(
+                ResponseTemplate::new(status)
+                    .insert_header(
+                        "x-amzn-ErrorType",
+                        format!("{}:http://internal", error_type).as_str(),
+                    )
+                    .set_body_json(serde_json::json!({ "message": "Nope" })),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let error = email_client
+            .send_email("ursula@example.com", "Hello", "<p>Hi</p>", "Hi")
+            .await
+            .unwrap_err();
+
+        assert_provider_error(&error, "ses", category);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mailgun_sends_a_multipart_form_with_basic_auth() -> Result<()> {
+    let mock_server = MockServer::start().await;
+    let email_client = mailgun_email_client(&mock_server)?;
+
+    Mock::given(path("/v3/mg.example.com/messages"))
+        .and(method("POST"))
+        // "api:key-123", base64-encoded.
+        .and(header("Authorization", "Basic YXBpOmtleS0xMjM="))
+        .and(header_regex(
+            "Content-Type",
+            "^multipart/form-data; boundary=",
+        ))
+        .and(MultipartFieldsMatcher(&[
+            ("from", "newsletter@example.com"),
+            ("to", "ursula@example.com"),
+            ("subject", "Hello"),
+            ("text", "Hi"),
+            ("html", "<p>Hi</p>"),
+            ("h:X-Test", "1"),
+        ]))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "<1@mg.example.com>",
+            "message": "Queued. Thank you."
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    email_client
+        .send_email_with_headers(
+            "ursula@example.com",
+            "Hello",
+            "<p>Hi</p>",
+            "Hi",
+            &[("X-Test", "1")],
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mailgun_errors_are_mapped_onto_common_categories() -> Result<()> {
+    let test_cases = [
+        (429, "Too many requests", EmailErrorCategory::Throttled),
+        (
+            400,
+            "'to' parameter is not a valid address. please check documentation",
+            EmailErrorCategory::RejectedRecipient,
+        ),
+        (401, "Forbidden", EmailErrorCategory::AuthFailure),
+        (
+            503,
+            "Service unavailable",
+            EmailErrorCategory::ProviderUnavailable,
+        ),
+        (
+            400,
+            "'from' parameter is missing",
+            EmailErrorCategory::InvalidRequest,
+        ),
+    ];
+
+    for (status, message, category) in test_cases {
+        let mock_server = MockServer::start().await;
+        let email_client = mailgun_email_client(&mock_server)?;
+
+        Mock::given(any())
+            .respond_with(
+                ResponseTemplate::new(status)
+                    .set_body_json(serde_json::json!({ "message": message })),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let error = email_client
+            .send_email("ursula@example.com", "Hello", "<p>Hi</p>", "Hi")
+            .await
+            .unwrap_err();
+
+        assert_provider_error(&error, "mailgun", category);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn the_transport_is_chosen_by_the_kind_setting() -> Result<()> {
+    for kind in ["postmark", "smtp", "ses", "mailgun", "in_memory"] {
+        let settings: EmailClientSettings = Config::builder()
+            .add_source(config::File::from_str(
+                &format!(
+                    r#"
+                    kind: "{}"
+                    sender_email: "newsletter@example.com"
+                    timeout_milliseconds: 1000
+                    base_url: "http://localhost"
+                    authorization_token: "token"
+                    smtp:
+                      host: "localhost"
+                    ses:
+                      region: "eu-west-1"
+                      access_key_id: "AKIDEXAMPLE"
+                      secret_access_key: "secret"
+                    mailgun:
+                      domain: "mg.example.com"
+                      api_key: "key-123"
+                    "#,
+                    kind
+                ),
+                FileFormat::Yaml,
+            ))
+            .build()?
+            .try_deserialize()?;
+
+        let email_client = settings.client()?;
+
+        let transport = format!("{:?}", email_client);
+        let expected = match kind {
+            "postmark" => "PostmarkTransport",
+            "smtp" => "SmtpTransport",
+            "ses" => "SesTransport",
+            "mailgun" => "MailgunTransport",
+            _ => "InMemoryTransport",
+        };
+        assert!(transport.contains(expected), "{} built {}", kind, transport);
+    }
+
+    Ok(())
+}
+
+fn assert_provider_error(error: &anyhow::Error, provider: &str, category: EmailErrorCategory) {
+    let provider_error = error
+        .downcast_ref::<EmailProviderError>()
+        .expect("Expected a provider error");
+    assert_eq!(provider_error.provider, provider);
+    assert_eq!(provider_error.category, category, "{}", provider_error);
+    assert_eq!(is_transient_error(error), category.is_transient());
+}
+
+fn ses_email_client(mock_server: &MockServer) -> Result<EmailClient> {
+    let transport = SesTransport::new(
+        "eu-west-1".into(),
+        "AKIDEXAMPLE".into(),
+        Secret::new("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into()),
+        Some(mock_server.uri()),
+        Duration::from_secs(5),
+    )?;
+
+    Ok(EmailClient::new("newsletter@example.com".into(), transport))
+}
+
+fn mailgun_email_client(mock_server: &MockServer) -> Result<EmailClient> {
+    let transport = MailgunTransport::new(
+        mock_server.uri(),
+        "mg.example.com".into(),
+        Secret::new("key-123".into()),
+        Duration::from_secs(5),
+    )?;
+
+    Ok(EmailClient::new("newsletter@example.com".into(), transport))
+}
+
+/// Matches requests whose `Authorization` header is the expected SES signature.
+struct SesSignatureMatcher;
+
+impl Match for SesSignatureMatcher {
+    fn matches(&self, request: &Request) -> bool {
+        let header = |name: &str| {
+            request.headers.get(&name.into()).map(|values| {
+                values
+                    .iter()
+                    .map(|v| v.as_str().trim())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+        };
+        let (Some(amz_date), Some(host), Some(authorization)) = (
+            header("x-amz-date"),
+            header("host"),
+            header("authorization"),
+        ) else {
+            return false;
+        };
+        let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(&amz_date, "%Y%m%dT%H%M%SZ")
+        else {
+            return false;
+        };
+
+        let expected = sigv4_authorization(
+            "POST",
+            request.url.path(),
+            &[
+                ("content-type", "application/json"),
+                ("host", &host),
+                ("x-amz-date", &amz_date),
+            ],
+            &request.body,
+            &SigningParams {
+                access_key_id: "AKIDEXAMPLE",
+                secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                region: "eu-west-1",
+                service: "ses",
+                datetime: datetime.and_utc(),
+            },
+        );
+
+        // The mock server splits header values on commas.
+        authorization == expected.replace(", ", ",")
+    }
+}
+
+/// Matches multipart form bodies containing every given field.
+struct MultipartFieldsMatcher(&'static [(&'static str, &'static str)]);
+
+impl Match for MultipartFieldsMatcher {
+    fn matches(&self, request: &Request) -> bool {
+        let body = String::from_utf8_lossy(&request.body);
+
+        self.0.iter().all(|(name, value)| {
+            body.contains(&format!(
+                "Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                name, value
+            ))
+        })
+    }
+}
+
+fn smtp_email_client(server: &FakeSmtpServer, mechanism: SmtpAuthMechanism) -> Result<EmailClient> {
+    let settings = SmtpSettings {
+        host: "127.0.0.1".into(),
+        port: Some(server.port),
+        tls: SmtpTls::None,
+        username: Some("user".into()),
+        password: Some(Secret::new("password".into())),
+        auth_mechanisms: vec![mechanism],
+    };
+    let transport = SmtpTransport::new(&settings, Duration::from_secs(5))?;
+
+    Ok(EmailClient::new("newsletter@example.com".into(), transport))
+}
+
+/// Speaks just enough SMTP to accept a single email, recording everything the client sends.
+struct FakeSmtpServer {
+    port: u16,
+    session: tokio::task::JoinHandle<Result<String>>,
+}
+
+impl FakeSmtpServer {
+    /// `data_reply` is the reply code sent once the message content has been received.
+    async fn start(data_reply: u16) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let session = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            serve_smtp_session(stream, data_reply).await
+        });
+
+        Ok(Self { port, session })
+    }
+
+    async fn transcript(self) -> Result<String> {
+        self.session.await?
+    }
+}
+
+async fn serve_smtp_session(stream: TcpStream, data_reply: u16) -> Result<String> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut transcript = String::new();
+    let mut in_data = false;
+
+    writer.write_all(b"220 localhost ESMTP\r\n").await?;
+    while let Some(line) = lines.next_line().await? {
+        transcript.push_str(&line);
+        transcript.push('\n');
+
+        let reply: &[u8] = if in_data {
+            if line != "." {
+                continue;
+            }
+            in_data = false;
+            match data_reply {
+                250 => b"250 OK\r\n",
+                451 => b"451 Try again later\r\n",
+                _ => b"550 Mailbox unavailable\r\n",
+            }
+        } else if line.starts_with("EHLO") {
+            b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n"
+        } else if line == "AUTH LOGIN" {
+            b"334 VXNlcm5hbWU6\r\n"
+        } else if line == "dXNlcg==" {
+            b"334 UGFzc3dvcmQ6\r\n"
+        } else if line.starts_with("AUTH PLAIN") || line == "cGFzc3dvcmQ=" {
+            b"235 Authenticated\r\n"
+        } else if line == "DATA" {
+            in_data = true;
+            b"354 Go ahead\r\n"
+        } else if line == "QUIT" {
+            writer.write_all(b"221 Bye\r\n").await?;
+            break;
+        } else {
+            b"250 OK\r\n"
+        };
+        writer.write_all(reply).await?;
+    }
+
+    Ok(transcript)
+}
+
 struct SendEmailBodyMatcher;
 
 impl Match for SendEmailBodyMatcher {
@@ -120,9 +768,7 @@ fn email() -> String {
 
 fn email_client(uri: String) -> EmailClient {
     EmailClient::new(
-        uri,
         email(),
-        Secret::new(Faker.fake()),
-        Duration::from_millis(200),
+        PostmarkTransport::new(uri, Secret::new(Faker.fake()), Duration::from_millis(200)),
     )
 }
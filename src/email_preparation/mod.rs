@@ -0,0 +1,42 @@
+use anyhow::{Context, Result};
+use css_inline::CSSInliner;
+
+/// Plain-text bodies are wrapped at this many columns.
+const TEXT_WIDTH: usize = 78;
+
+/// Moves the rules of `<style>` tags into `style` attributes, since many mail clients drop
+/// stylesheets. Linked stylesheets are never fetched.
+///
+/// Only the content of the `<body>` is returned, whether the input is a fragment or a whole
+/// document, so that it can be embedded in the email template.
+pub fn inline_css(html: &str) -> Result<String> {
+    let inlined = CSSInliner::options()
+        .load_remote_stylesheets(false)
+        .build()
+        .inline(html)
+        .context("Failed to inline the CSS of an HTML email")?;
+
+    Ok(body_content(&inlined).unwrap_or(&inlined).to_owned())
+}
+
+/// The markup between `<body ...>` and `</body>`, matched case-insensitively.
+fn body_content(document: &str) -> Option<&str> {
+    // ASCII lowercasing keeps byte offsets unchanged.
+    let lowercase = document.to_ascii_lowercase();
+    let body_tag = lowercase.find("<body")?;
+    let start = body_tag + lowercase[body_tag..].find('>')? + 1;
+    let end = lowercase.rfind("</body>")?;
+    document.get(start..end)
+}
+
+/// Produces a readable plain-text alternative to an HTML body. Links are numbered in the text
+/// and listed as footnotes at the end.
+pub fn html_to_text(html: &str) -> Result<String> {
+    html2text::config::plain_no_decorate()
+        .link_footnotes(true)
+        .string_from_read(html.as_bytes(), TEXT_WIDTH)
+        .context("Failed to convert an HTML email to plain text")
+}
+
+#[cfg(test)]
+mod tests;
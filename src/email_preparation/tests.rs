@@ -0,0 +1,67 @@
+use super::*;
+
+#[test]
+fn style_rules_are_moved_into_style_attributes() {
+    let html = "<style>h1 { color: blue; }</style><h1>Big news</h1>";
+
+    let inlined = inline_css(html).unwrap();
+
+    assert!(inlined.contains(r#"<h1 style="color: blue;">Big news</h1>"#));
+    assert!(!inlined.contains("<style>"));
+}
+
+#[test]
+fn fragments_are_not_wrapped_in_a_document() {
+    let html = "<style>h1 { color: blue; }</style><h1>Big news</h1>";
+
+    let inlined = inline_css(html).unwrap();
+
+    assert_eq!(inlined, r#"<h1 style="color: blue;">Big news</h1>"#);
+}
+
+#[test]
+fn documents_are_reduced_to_their_body() {
+    let html = "<!DOCTYPE html><html><head><style>h1 { color: blue; }</style></head>\
+        <BODY class=\"issue\" style=\"margin: 0\"><h1>Big news</h1></BODY></html>";
+
+    let inlined = inline_css(html).unwrap();
+
+    assert_eq!(inlined, r#"<h1 style="color: blue;">Big news</h1>"#);
+}
+
+#[test]
+fn existing_style_attributes_take_precedence() {
+    let html = r#"<style>p { color: blue; }</style><p style="color: red;">Hi</p>"#;
+
+    let inlined = inline_css(html).unwrap();
+
+    assert!(inlined.contains(r#"<p style="color: red">Hi</p>"#));
+}
+
+#[test]
+fn linked_stylesheets_are_not_fetched() {
+    let html = r#"<link rel="stylesheet" href="http://127.0.0.1:1/style.css"><p>Hi</p>"#;
+
+    assert!(inline_css(html).is_ok());
+}
+
+#[test]
+fn links_become_footnotes_in_the_text_body() {
+    let html = r#"<h1>Issue 1</h1><p>Read <a href="https://example.com/post">the post</a>.</p>"#;
+
+    let text = html_to_text(html).unwrap();
+
+    assert!(text.contains("Issue 1"));
+    assert!(text.contains("Read [the post][1]."));
+    assert!(text.contains("[1]: https://example.com/post"));
+    assert!(!text.contains('<'));
+}
+
+#[test]
+fn style_tags_do_not_leak_into_the_text_body() {
+    let html = "<style>p { color: blue; }</style><p>Hello</p>";
+
+    let text = html_to_text(html).unwrap();
+
+    assert_eq!(text.trim(), "Hello");
+}
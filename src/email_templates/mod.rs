@@ -0,0 +1,101 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use minijinja::{escape_formatter, AutoEscape, Environment, UndefinedBehavior};
+use serde::Serialize;
+
+/// The HTML and plain-text bodies of an email rendered from a template.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RenderedEmail {
+    pub html: String,
+    pub text: String,
+}
+
+/// A typed context for a named template, rendered from `<NAME>.html` and `<NAME>.txt`.
+pub trait EmailTemplate: Serialize {
+    const NAME: &'static str;
+}
+
+#[derive(Serialize)]
+pub struct ConfirmationEmail<'a> {
+    pub subscriber_name: &'a str,
+    pub confirmation_link: &'a str,
+}
+
+impl EmailTemplate for ConfirmationEmail<'_> {
+    const NAME: &'static str = "confirmation";
+}
+
+/// Wraps an issue's content. The HTML content comes from an admin and is not escaped.
+#[derive(Serialize)]
+pub struct NewsletterIssueEmail<'a> {
+    pub html_content: &'a str,
+    pub text_content: &'a str,
+    pub unsubscribe_link: &'a str,
+}
+
+impl EmailTemplate for NewsletterIssueEmail<'_> {
+    const NAME: &'static str = "newsletter_issue";
+}
+
+const TEMPLATE_NAMES: [&str; 2] = [ConfirmationEmail::NAME, NewsletterIssueEmail::NAME];
+
+/// Email templates loaded from a directory. `.html` templates are auto-escaped.
+#[derive(Clone, Debug)]
+pub struct EmailTemplates {
+    environment: Arc<Environment<'static>>,
+}
+
+impl EmailTemplates {
+    /// Loads and parses every known template, so that missing files and syntax errors surface at
+    /// startup rather than when the first email goes out.
+    pub fn from_directory(directory: impl AsRef<Path>) -> Result<Self> {
+        let directory = directory.as_ref();
+        let mut environment = Environment::new();
+        environment.set_undefined_behavior(UndefinedBehavior::Strict);
+        // The default escaper also encodes `/`, which mangles links for no benefit.
+        environment.set_formatter(|out, state, value| {
+            if state.auto_escape() == AutoEscape::Html && !value.is_safe() {
+                out.write_str(&htmlescape::encode_minimal(&value.to_string()))?;
+                Ok(())
+            } else {
+                escape_formatter(out, state, value)
+            }
+        });
+
+        for name in TEMPLATE_NAMES {
+            for extension in ["html", "txt"] {
+                let file_name = format!("{}.{}", name, extension);
+                let path = directory.join(&file_name);
+                let source = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read the {} template", path.display()))?;
+                environment
+                    .add_template_owned(file_name, source)
+                    .with_context(|| format!("Invalid template in {}", path.display()))?;
+            }
+        }
+
+        Ok(Self {
+            environment: Arc::new(environment),
+        })
+    }
+
+    pub fn render<T: EmailTemplate>(&self, context: &T) -> Result<RenderedEmail> {
+        let render = |extension| {
+            let name = format!("{}.{}", T::NAME, extension);
+            self.environment
+                .get_template(&name)?
+                .render(context)
+                .with_context(|| format!("Failed to render the {} template", name))
+        };
+
+        Ok(RenderedEmail {
+            html: render("html")?,
+            text: render("txt")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
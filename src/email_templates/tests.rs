@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use claims::assert_err;
+use uuid::Uuid;
+
+use crate::email_templates::{ConfirmationEmail, EmailTemplates, NewsletterIssueEmail};
+
+fn repository_templates() -> EmailTemplates {
+    EmailTemplates::from_directory("templates").unwrap()
+}
+
+/// Copies the repository templates to a scratch directory, replacing `file_name` with `source`.
+fn templates_directory_with(file_name: &str, source: Option<&str>) -> PathBuf {
+    let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&directory).unwrap();
+    for entry in std::fs::read_dir("templates").unwrap() {
+        let entry = entry.unwrap();
+        std::fs::copy(entry.path(), directory.join(entry.file_name())).unwrap();
+    }
+    match source {
+        Some(source) => std::fs::write(directory.join(file_name), source).unwrap(),
+        None => std::fs::remove_file(directory.join(file_name)).unwrap(),
+    }
+    directory
+}
+
+#[test]
+fn the_confirmation_email_contains_the_link_and_the_name() {
+    let email = repository_templates()
+        .render(&ConfirmationEmail {
+            subscriber_name: "Ursula",
+            confirmation_link: "https://example.com/confirm?subscription_token=abc",
+        })
+        .unwrap();
+
+    assert!(email.html.contains("Ursula"));
+    assert!(email
+        .html
+        .contains(r#"href="https://example.com/confirm?subscription_token=abc""#));
+    assert!(email
+        .text
+        .contains("https://example.com/confirm?subscription_token=abc"));
+}
+
+#[test]
+fn html_templates_escape_their_context() {
+    let email = repository_templates()
+        .render(&ConfirmationEmail {
+            subscriber_name: "<script>alert(1)</script>",
+            confirmation_link: r#"https://example.com/"><script>"#,
+        })
+        .unwrap();
+
+    assert!(!email.html.contains("<script>"));
+    assert!(email.html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+    // Plain-text bodies are not HTML and are left alone.
+    assert!(email.text.contains("<script>alert(1)</script>"));
+}
+
+#[test]
+fn newsletter_issues_keep_their_html_and_get_an_unsubscribe_link() {
+    let email = repository_templates()
+        .render(&NewsletterIssueEmail {
+            html_content: "<p>Newsletter body</p>",
+            text_content: "Newsletter body",
+            unsubscribe_link: "https://example.com/subscriptions/unsubscribe?token=a.b",
+        })
+        .unwrap();
+
+    assert!(email.html.contains("<body>\n<p>Newsletter body</p>"));
+    assert!(email
+        .html
+        .contains(r#"href="https://example.com/subscriptions/unsubscribe?token=a.b""#));
+    assert!(email.text.starts_with("Newsletter body"));
+    assert!(email
+        .text
+        .contains("https://example.com/subscriptions/unsubscribe?token=a.b"));
+}
+
+#[test]
+fn syntax_errors_are_reported_when_loading() {
+    let directory =
+        templates_directory_with("confirmation.html", Some("<p>{{ subscriber_name </p>"));
+
+    let error = EmailTemplates::from_directory(&directory).unwrap_err();
+
+    assert!(format!("{:#}", error).contains("confirmation.html"));
+}
+
+#[test]
+fn missing_templates_are_reported_when_loading() {
+    let directory = templates_directory_with("newsletter_issue.txt", None);
+
+    assert_err!(EmailTemplates::from_directory(&directory));
+}
@@ -0,0 +1,3 @@
+pub use persistence::*;
+
+mod persistence;
@@ -0,0 +1,141 @@
+use actix_web::body::to_bytes;
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use sqlx::postgres::PgHasArrayType;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::authentication::UserId;
+use crate::domain::IdempotencyKey;
+
+#[derive(Debug, sqlx::Type)]
+#[sqlx(type_name = "header_pair")]
+struct HeaderPairRecord {
+    name: String,
+    value: Vec<u8>,
+}
+
+impl PgHasArrayType for HeaderPairRecord {
+    fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("_header_pair")
+    }
+}
+
+pub enum NextAction {
+    StartProcessing(Box<Transaction<'static, Postgres>>),
+    ReturnSavedResponse(HttpResponse),
+}
+
+pub async fn try_processing(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    user_id: UserId,
+) -> Result<NextAction> {
+    let mut transaction = pool.begin().await?;
+
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO idempotency (user_id, idempotency_key, created_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id.as_ref(),
+        idempotency_key.as_ref(),
+        Utc::now(),
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to insert the idempotency key")?
+    .rows_affected();
+
+    if n_inserted_rows > 0 {
+        return Ok(NextAction::StartProcessing(Box::new(transaction)));
+    }
+
+    let saved_response = get_saved_response(pool, idempotency_key, user_id)
+        .await?
+        .ok_or_else(|| anyhow!("We expected a saved response, we didn't find it"))?;
+
+    Ok(NextAction::ReturnSavedResponse(saved_response))
+}
+
+async fn get_saved_response(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    user_id: UserId,
+) -> Result<Option<HttpResponse>> {
+    let saved_response = sqlx::query!(
+        r#"
+        SELECT
+            response_status_code AS "response_status_code!",
+            response_headers AS "response_headers!: Vec<HeaderPairRecord>",
+            response_body AS "response_body!"
+        FROM idempotency
+        WHERE user_id = $1 AND idempotency_key = $2
+        "#,
+        user_id.as_ref(),
+        idempotency_key.as_ref(),
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the saved response")?;
+
+    let Some(r) = saved_response else {
+        return Ok(None);
+    };
+
+    let status_code = StatusCode::from_u16(r.response_status_code.try_into()?)?;
+    let mut response = HttpResponse::build(status_code);
+    for HeaderPairRecord { name, value } in r.response_headers {
+        response.append_header((name, value));
+    }
+
+    Ok(Some(response.body(r.response_body)))
+}
+
+pub async fn save_response(
+    mut transaction: Transaction<'static, Postgres>,
+    idempotency_key: &IdempotencyKey,
+    user_id: UserId,
+    http_response: HttpResponse,
+) -> Result<HttpResponse> {
+    let (response_head, body) = http_response.into_parts();
+    let body = to_bytes(body)
+        .await
+        .map_err(|e| anyhow!("Failed to read the response body: {}", e))?;
+    let status_code = response_head.status().as_u16() as i16;
+    let headers = response_head
+        .headers()
+        .iter()
+        .map(|(name, value)| HeaderPairRecord {
+            name: name.as_str().to_owned(),
+            value: value.as_bytes().to_owned(),
+        })
+        .collect::<Vec<_>>();
+
+    sqlx::query_unchecked!(
+        r#"
+        UPDATE idempotency
+        SET
+            response_status_code = $3,
+            response_headers = $4,
+            response_body = $5
+        WHERE user_id = $1 AND idempotency_key = $2
+        "#,
+        user_id.as_ref(),
+        idempotency_key.as_ref(),
+        status_code,
+        headers,
+        body.as_ref(),
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to save the response")?;
+
+    transaction.commit().await?;
+
+    let http_response = response_head.set_body(body).map_into_boxed_body();
+
+    Ok(http_response)
+}
@@ -0,0 +1,360 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use rand::{thread_rng, Rng};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{error, field, warn, Span};
+use uuid::Uuid;
+
+use crate::domain::SubscriberEmail;
+use crate::email_client::{is_transient_error, EmailClient};
+use crate::email_templates::{EmailTemplates, NewsletterIssueEmail};
+use crate::shutdown::ShutdownHandle;
+use crate::unsubscribe::UnsubscribeLinks;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: i16,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn backoff(&self, n_attempts: i16) -> Duration {
+        let exponent = n_attempts.saturating_sub(1).clamp(0, 31) as u32;
+        let ceiling = self
+            .base_backoff
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(self.max_backoff);
+
+        // Equal jitter: wait at least half of the exponential delay, plus a random share of the rest.
+        let half = ceiling / 2;
+        half + thread_rng().gen_range(Duration::ZERO..=ceiling - half)
+    }
+}
+
+pub async fn run_worker_until_stopped(
+    pool: PgPool,
+    email_client: EmailClient,
+    email_templates: EmailTemplates,
+    retry_policy: RetryPolicy,
+    unsubscribe_links: UnsubscribeLinks,
+    shutdown: ShutdownHandle,
+) -> Result<()> {
+    // A delivery that has started is always finished; shutdown is only checked between tasks.
+    while !shutdown.is_triggered() {
+        let pause = match try_execute_task(
+            &pool,
+            &email_client,
+            &email_templates,
+            &retry_policy,
+            &unsubscribe_links,
+        )
+        .await
+        {
+            Ok(ExecutionOutcome::EmptyQueue) => Duration::from_secs(10),
+            Err(_) => Duration::from_secs(1),
+            Ok(ExecutionOutcome::TaskCompleted) => continue,
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(pause) => {}
+            _ = shutdown.triggered() => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(newsletter_issue_id = field::Empty, subscriber_id = field::Empty),
+    err
+)]
+pub async fn try_execute_task(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    email_templates: &EmailTemplates,
+    retry_policy: &RetryPolicy,
+    unsubscribe_links: &UnsubscribeLinks,
+) -> Result<ExecutionOutcome> {
+    let Some((transaction, task)) = dequeue_task(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current()
+        .record(
+            "newsletter_issue_id",
+            field::display(task.newsletter_issue_id),
+        )
+        .record("subscriber_id", field::display(task.subscriber_id));
+
+    match deliver_issue(
+        pool,
+        email_client,
+        email_templates,
+        unsubscribe_links,
+        &task,
+    )
+    .await
+    {
+        Ok(()) => delete_task(transaction, &task).await?,
+        Err(e) => {
+            let n_attempts = task.n_attempts + 1;
+            if e.is_transient() && n_attempts < retry_policy.max_attempts {
+                let backoff = retry_policy.backoff(n_attempts);
+                let e = e.into_inner();
+                warn!(
+                    ?e,
+                    n_attempts,
+                    ?backoff,
+                    "Failed to deliver issue. Retrying later"
+                );
+                reschedule_task(transaction, &task, n_attempts, backoff).await?;
+            } else {
+                let e = e.into_inner();
+                error!(?e, n_attempts, "Failed to deliver issue. Giving up");
+                move_to_failed_deliveries(transaction, &task, n_attempts, &e).await?;
+            }
+        }
+    }
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+/// Why an attempt to deliver an issue to a subscriber failed.
+enum DeliveryError {
+    /// The email could not be put together, e.g. because the database was unavailable.
+    /// Retried until the task runs out of attempts.
+    Preparation(anyhow::Error),
+    /// The email provider did not accept the email.
+    Send(anyhow::Error),
+}
+
+impl DeliveryError {
+    fn is_transient(&self) -> bool {
+        match self {
+            DeliveryError::Preparation(_) => true,
+            DeliveryError::Send(e) => is_transient_error(e),
+        }
+    }
+
+    fn into_inner(self) -> anyhow::Error {
+        match self {
+            DeliveryError::Preparation(e) | DeliveryError::Send(e) => e,
+        }
+    }
+}
+
+/// Sends the issue to the task's subscriber, unless they can no longer receive it.
+async fn deliver_issue(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    email_templates: &EmailTemplates,
+    unsubscribe_links: &UnsubscribeLinks,
+    task: &DeliveryTask,
+) -> Result<(), DeliveryError> {
+    // The subscriber may have left after the issue was enqueued.
+    let Some(subscriber_email) = get_confirmed_subscriber_email(pool, task.subscriber_id)
+        .await
+        .map_err(DeliveryError::Preparation)?
+    else {
+        return Ok(());
+    };
+
+    let subscriber_email = match SubscriberEmail::try_from(subscriber_email) {
+        Ok(subscriber_email) => subscriber_email,
+        Err(e) => {
+            warn!(
+                ?e,
+                "Skipping a confirmed subscriber. Their stored contact details are invalid"
+            );
+            return Ok(());
+        }
+    };
+
+    let issue = get_issue(pool, task.newsletter_issue_id)
+        .await
+        .map_err(DeliveryError::Preparation)?;
+    let unsubscribe_link = unsubscribe_links.link(task.subscriber_id);
+    let body = email_templates
+        .render(&NewsletterIssueEmail {
+            html_content: &issue.html_content,
+            text_content: &issue.text_content,
+            unsubscribe_link: &unsubscribe_link,
+        })
+        .map_err(DeliveryError::Preparation)?;
+    let list_unsubscribe = format!("<{}>", unsubscribe_link);
+    email_client
+        .send_email_with_headers(
+            subscriber_email.as_ref(),
+            &issue.title,
+            &body.html,
+            &body.text,
+            &[
+                ("List-Unsubscribe", &list_unsubscribe),
+                ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+            ],
+        )
+        .await
+        .map_err(DeliveryError::Send)
+}
+
+struct DeliveryTask {
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    n_attempts: i16,
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(pool: &PgPool) -> Result<Option<(PgTransaction, DeliveryTask)>> {
+    let mut transaction = pool.begin().await?;
+
+    let task = sqlx::query_as!(
+        DeliveryTask,
+        r#"
+        SELECT newsletter_issue_id, subscriber_id, n_attempts
+        FROM issue_delivery_queue
+        WHERE next_attempt_at <= now()
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to dequeue a delivery task")?;
+
+    Ok(task.map(|task| (transaction, task)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(mut transaction: PgTransaction, task: &DeliveryTask) -> Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1 AND subscriber_id = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete a delivery task")?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction, task))]
+async fn reschedule_task(
+    mut transaction: PgTransaction,
+    task: &DeliveryTask,
+    n_attempts: i16,
+    backoff: Duration,
+) -> Result<()> {
+    let next_attempt_at = Utc::now() + chrono::Duration::from_std(backoff)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET n_attempts = $3, next_attempt_at = $4
+        WHERE newsletter_issue_id = $1 AND subscriber_id = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+        n_attempts,
+        next_attempt_at,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to reschedule a delivery task")?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction, task, last_error))]
+async fn move_to_failed_deliveries(
+    mut transaction: PgTransaction,
+    task: &DeliveryTask,
+    n_attempts: i16,
+    last_error: &anyhow::Error,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO failed_deliveries (
+            newsletter_issue_id, subscriber_id, n_attempts, last_error, failed_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET n_attempts = EXCLUDED.n_attempts,
+            last_error = EXCLUDED.last_error,
+            failed_at = EXCLUDED.failed_at
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+        n_attempts,
+        format!("{:#}", last_error),
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store a failed delivery")?;
+
+    delete_task(transaction, task).await
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_confirmed_subscriber_email(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<String>> {
+    let subscriber = sqlx::query!(
+        r#"
+        SELECT email
+        FROM subscriptions
+        WHERE id = $1 AND status = 'confirmed'
+        "#,
+        subscriber_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the subscriber")?;
+
+    Ok(subscriber.map(|s| s.email))
+}
+
+struct NewsletterIssue {
+    title: String,
+    text_content: String,
+    html_content: String,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue> {
+    let issue = sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT title, text_content, html_content
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve the newsletter issue")?;
+
+    Ok(issue)
+}
+
+#[cfg(test)]
+mod tests;
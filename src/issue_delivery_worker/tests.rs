@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use crate::issue_delivery_worker::RetryPolicy;
+
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 5,
+        base_backoff: Duration::from_secs(10),
+        max_backoff: Duration::from_secs(60),
+    }
+}
+
+#[test]
+fn backoff_grows_exponentially_with_the_number_of_attempts() {
+    let policy = retry_policy();
+
+    for (n_attempts, ceiling) in [(1, 10), (2, 20), (3, 40)] {
+        let ceiling = Duration::from_secs(ceiling);
+        for _ in 0..100 {
+            let backoff = policy.backoff(n_attempts);
+            assert!(
+                backoff >= ceiling / 2,
+                "{:?} is below {:?}",
+                backoff,
+                ceiling / 2
+            );
+            assert!(backoff <= ceiling, "{:?} is above {:?}", backoff, ceiling);
+        }
+    }
+}
+
+#[test]
+fn backoff_is_capped() {
+    let policy = retry_policy();
+
+    for n_attempts in [4, 10, i16::MAX] {
+        assert!(policy.backoff(n_attempts) <= policy.max_backoff);
+    }
+}
+
+#[test]
+fn backoff_is_jittered() {
+    let policy = retry_policy();
+
+    let first = policy.backoff(3);
+    let differs = (0..100).any(|_| policy.backoff(3) != first);
+
+    assert!(differs);
+}
@@ -1,6 +1,19 @@
+pub mod authentication;
 pub mod configuration;
 pub mod domain;
+pub mod domain_verification;
 pub mod email_client;
+pub mod email_preparation;
+pub mod email_templates;
+pub mod idempotency;
+pub mod issue_delivery_worker;
+pub mod metrics;
+pub mod rate_limit;
 pub mod routes;
+pub mod session;
+pub mod shutdown;
 pub mod startup;
+pub mod subscription_token_cleanup;
 pub mod telemetry;
+pub mod unsubscribe;
+pub mod utils;
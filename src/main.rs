@@ -1,19 +1,34 @@
 use anyhow::Result;
+use tracing::error;
 
 use zero2prod::configuration::Settings;
+use zero2prod::shutdown::shutdown_signal;
 use zero2prod::startup::Application;
-use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::telemetry::{get_subscriber, init_subscriber, shutdown_tracer_provider};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let subscriber = get_subscriber("zero2prod".into(), "info", std::io::stdout);
-    init_subscriber(subscriber)?;
-
     let configuration = Settings::get_configuration()?;
 
+    let subscriber = get_subscriber(
+        "zero2prod".into(),
+        "info",
+        std::io::stdout,
+        configuration.telemetry.otlp.as_ref(),
+    )?;
+    init_subscriber(subscriber)?;
+
     let application = Application::build(configuration).await?;
+    let shutdown = application.shutdown_handle();
+    tokio::spawn(async move {
+        match shutdown_signal().await {
+            Ok(()) => shutdown.trigger(),
+            Err(e) => error!(?e, "Failed to listen for shutdown signals"),
+        }
+    });
 
-    application.run_until_stopped().await?;
+    let outcome = application.run_until_stopped().await;
+    tokio::task::spawn_blocking(shutdown_tracer_provider).await?;
 
-    Ok(())
+    outcome
 }
@@ -0,0 +1,142 @@
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web_lab::middleware::Next;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder,
+    HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use sqlx::PgPool;
+
+// Metrics live in the default registry: every `Application` in the process shares them.
+
+static HTTP_REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "http_requests_total",
+        "HTTP requests handled, by route and status.",
+        &["method", "route", "status"]
+    )
+    .unwrap()
+});
+
+static HTTP_REQUEST_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Time spent handling HTTP requests, by route and status.",
+        &["method", "route", "status"]
+    )
+    .unwrap()
+});
+
+static DB_POOL_CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "db_pool_connections",
+        "Connections in the Postgres pool, by state: open, idle, in_use or max.",
+        &["state"]
+    )
+    .unwrap()
+});
+
+static EMAIL_SENDS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "email_sends_total",
+        "Emails handed to a provider, by outcome.",
+        &["provider", "outcome"]
+    )
+    .unwrap()
+});
+
+static EMAIL_SEND_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "email_send_duration_seconds",
+        "Time spent handing emails to a provider.",
+        &["provider"]
+    )
+    .unwrap()
+});
+
+static EMAIL_SEND_FAILURES_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "email_send_failures_total",
+        "Emails a provider failed to accept, by the code it answered with.",
+        &["provider", "code"]
+    )
+    .unwrap()
+});
+
+/// Records the count and latency of every request, labelled with the matched route pattern
+/// rather than the raw path to keep the number of series bounded.
+pub async fn record_http_metrics<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let result = next.call(req).await;
+
+    let status = match &result {
+        Ok(response) => response.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    HTTP_REQUESTS_TOTAL.with_label_values(&labels).inc();
+    HTTP_REQUEST_DURATION_SECONDS
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+
+    result
+}
+
+/// Snapshots the state of the connection pool. sqlx does not expose how many tasks are waiting
+/// for a connection, so saturation shows up as `in_use` reaching `max`.
+pub fn record_pool_state(pool: &PgPool) {
+    let open = pool.size() as i64;
+    let idle = pool.num_idle() as i64;
+    DB_POOL_CONNECTIONS.with_label_values(&["open"]).set(open);
+    DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["in_use"])
+        .set(open - idle);
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["max"])
+        .set(pool.options().get_max_connections() as i64);
+}
+
+/// Records one attempt to send an email. `failure_code` is the provider's response code, if the
+/// attempt failed.
+pub fn record_email_send(provider: &str, elapsed: Duration, failure_code: Option<&str>) {
+    EMAIL_SEND_DURATION_SECONDS
+        .with_label_values(&[provider])
+        .observe(elapsed.as_secs_f64());
+
+    let outcome = match failure_code {
+        None => "success",
+        Some(code) => {
+            EMAIL_SEND_FAILURES_TOTAL
+                .with_label_values(&[provider, code])
+                .inc();
+            "failure"
+        }
+    };
+    EMAIL_SENDS_TOTAL
+        .with_label_values(&[provider, outcome])
+        .inc();
+}
+
+/// Every registered metric in the Prometheus text exposition format.
+pub fn encode() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("Metrics can always be encoded as text");
+    String::from_utf8(buffer).expect("The text format is valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use super::*;
+
+#[test]
+fn successful_sends_are_counted_without_a_failure() {
+    record_email_send("metrics_test_success", Duration::from_millis(20), None);
+
+    let encoded = encode();
+    assert!(encoded
+        .contains(r#"email_sends_total{outcome="success",provider="metrics_test_success"} 1"#));
+    assert!(
+        encoded.contains(r#"email_send_duration_seconds_count{provider="metrics_test_success"} 1"#)
+    );
+    assert!(!encoded.lines().any(|line| {
+        line.starts_with("email_send_failures_total") && line.contains("metrics_test_success")
+    }));
+}
+
+#[test]
+fn failed_sends_are_counted_by_response_code() {
+    record_email_send(
+        "metrics_test_failure",
+        Duration::from_millis(20),
+        Some("503"),
+    );
+    record_email_send(
+        "metrics_test_failure",
+        Duration::from_millis(20),
+        Some("503"),
+    );
+    record_email_send(
+        "metrics_test_failure",
+        Duration::from_millis(20),
+        Some("none"),
+    );
+
+    let encoded = encode();
+    assert!(encoded
+        .contains(r#"email_sends_total{outcome="failure",provider="metrics_test_failure"} 3"#));
+    assert!(encoded
+        .contains(r#"email_send_failures_total{code="503",provider="metrics_test_failure"} 2"#));
+    assert!(encoded
+        .contains(r#"email_send_failures_total{code="none",provider="metrics_test_failure"} 1"#));
+}
@@ -0,0 +1,164 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use actix_web_lab::middleware::Next;
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+use secrecy::Secret;
+use sha2::{Digest, Sha256};
+use tracing::{error, warn};
+
+use crate::configuration::{RateLimitSettings, RateLimitStoreKind};
+use crate::utils::ProblemDetails;
+
+pub use store::*;
+
+mod store;
+
+/// A token bucket holding up to `capacity` requests, regaining one every `refill_interval`.
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub refill_interval: Duration,
+}
+
+impl RateLimit {
+    /// How far ahead of now a bucket may be drained before requests get rejected.
+    fn burst(&self) -> Duration {
+        self.refill_interval * self.capacity
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RateLimitOutcome {
+    Allowed,
+    Limited { retry_after: Duration },
+}
+
+/// Limits on `POST /subscriptions`, which sends an email on every call.
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: RateLimitStore,
+    per_ip: RateLimit,
+    per_email: RateLimit,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl RateLimiter {
+    pub async fn new(
+        settings: &RateLimitSettings,
+        redis_uri: Option<&Secret<String>>,
+    ) -> Result<Self> {
+        let store = match settings.store {
+            RateLimitStoreKind::InMemory => RateLimitStore::InMemory(Default::default()),
+            RateLimitStoreKind::Redis => {
+                let redis_uri = redis_uri
+                    .context("The Redis rate limit store requires `redis_uri` to be set")?;
+                RateLimitStore::Redis(RedisRateLimitStore::new(redis_uri).await?)
+            }
+        };
+
+        Ok(Self {
+            store,
+            per_ip: settings.per_ip.limit(),
+            per_email: settings.per_email.limit(),
+            trusted_proxies: settings.trusted_proxies.clone(),
+        })
+    }
+
+    pub async fn check_ip(&self, ip: IpAddr) -> RateLimitOutcome {
+        self.check(&format!("subscriptions:ip:{}", ip), &self.per_ip)
+            .await
+    }
+
+    /// Addresses are hashed so that the store never holds them in clear.
+    pub async fn check_email(&self, email: &str) -> RateLimitOutcome {
+        let digest = Sha256::digest(email.to_lowercase().as_bytes());
+        let key = format!("subscriptions:email:{:x}", digest);
+        self.check(&key, &self.per_email).await
+    }
+
+    async fn check(&self, key: &str, limit: &RateLimit) -> RateLimitOutcome {
+        // An unavailable store must not take subscriptions down with it.
+        self.store.check(key, limit).await.unwrap_or_else(|e| {
+            error!(
+                ?e,
+                "Failed to check a rate limit. Letting the request through"
+            );
+            RateLimitOutcome::Allowed
+        })
+    }
+}
+
+pub async fn limit_subscriptions_per_ip<B: MessageBody>(
+    rate_limiter: web::Data<RateLimiter>,
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let Some(peer_addr) = req.peer_addr() else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+    let ip = client_ip(peer_addr.ip(), req.headers(), &rate_limiter.trusted_proxies);
+
+    match rate_limiter.check_ip(ip).await {
+        RateLimitOutcome::Allowed => Ok(next.call(req).await?.map_into_left_body()),
+        RateLimitOutcome::Limited { retry_after } => {
+            warn!(%ip, "Rejected a subscription request over the per-IP limit");
+            let response = too_many_requests(
+                "Too many subscription requests from this address. Please try again later.",
+                retry_after,
+            );
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+/// The address a request originates from.
+///
+/// `X-Forwarded-For` is only honoured when the connection comes from a trusted proxy. The chain is
+/// then read right to left, skipping trusted proxies, so that a client cannot spoof its address
+/// by sending the header itself.
+pub fn client_ip(peer_ip: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|network| network.contains(ip));
+    if !is_trusted(&peer_ip) {
+        return peer_ip;
+    }
+
+    let forwarded_for: Vec<&str> = headers
+        .get_all("X-Forwarded-For")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    let mut client_ip = peer_ip;
+    for hop in forwarded_for.into_iter().rev() {
+        let Ok(hop) = hop.parse::<IpAddr>() else {
+            break;
+        };
+        client_ip = hop;
+        if !is_trusted(&hop) {
+            break;
+        }
+    }
+    client_ip
+}
+
+/// A `429 Too Many Requests` problem response telling the client when to come back.
+pub fn too_many_requests(detail: &str, retry_after: Duration) -> HttpResponse {
+    let mut response = ProblemDetails::new(StatusCode::TOO_MANY_REQUESTS, Some(detail.to_string()))
+        .into_response();
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(seconds.max(1)));
+    response
+}
+
+#[cfg(test)]
+mod tests;
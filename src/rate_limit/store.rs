@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use redis::aio::ConnectionManager;
+use redis::Script;
+use secrecy::{ExposeSecret, Secret};
+
+use super::{RateLimit, RateLimitOutcome};
+
+/// How often the in-process store drops buckets that have refilled completely.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where bucket state lives. Each bucket is tracked as a GCRA "theoretical arrival time", which
+/// behaves exactly like a token bucket while needing a single value per key.
+#[derive(Clone)]
+pub enum RateLimitStore {
+    InMemory(InMemoryRateLimitStore),
+    Redis(RedisRateLimitStore),
+}
+
+impl RateLimitStore {
+    pub async fn check(&self, key: &str, limit: &RateLimit) -> Result<RateLimitOutcome> {
+        match self {
+            Self::InMemory(store) => Ok(store.check(key, limit)),
+            Self::Redis(store) => store.check(key, limit).await,
+        }
+    }
+}
+
+/// Buckets local to this process: limits are not shared between replicas.
+#[derive(Clone, Default)]
+pub struct InMemoryRateLimitStore {
+    inner: Arc<Mutex<InMemoryBuckets>>,
+}
+
+#[derive(Default)]
+struct InMemoryBuckets {
+    arrival_times: HashMap<String, Instant>,
+    last_pruned: Option<Instant>,
+}
+
+impl InMemoryRateLimitStore {
+    pub fn check(&self, key: &str, limit: &RateLimit) -> RateLimitOutcome {
+        self.check_at(key, limit, Instant::now())
+    }
+
+    pub(super) fn check_at(&self, key: &str, limit: &RateLimit, now: Instant) -> RateLimitOutcome {
+        let mut buckets = self.inner.lock().unwrap();
+
+        if buckets
+            .last_pruned
+            .is_none_or(|t| now.saturating_duration_since(t) >= PRUNE_INTERVAL)
+        {
+            buckets.arrival_times.retain(|_, tat| *tat > now);
+            buckets.last_pruned = Some(now);
+        }
+
+        let tat = buckets
+            .arrival_times
+            .get(key)
+            .map_or(now, |tat| (*tat).max(now));
+        let new_tat = tat + limit.refill_interval;
+        let wait = new_tat.duration_since(now).saturating_sub(limit.burst());
+        if !wait.is_zero() {
+            return RateLimitOutcome::Limited { retry_after: wait };
+        }
+
+        buckets.arrival_times.insert(key.to_string(), new_tat);
+        RateLimitOutcome::Allowed
+    }
+}
+
+/// Buckets shared by every replica through Redis. Timestamps come from the Redis server clock.
+#[derive(Clone)]
+pub struct RedisRateLimitStore {
+    connection: ConnectionManager,
+    script: Arc<Script>,
+}
+
+impl RedisRateLimitStore {
+    pub async fn new(redis_uri: &Secret<String>) -> Result<Self> {
+        let client = redis::Client::open(redis_uri.expose_secret().as_str())
+            .context("Invalid Redis URI for the rate limit store")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to the Redis rate limit store")?;
+
+        Ok(Self {
+            connection,
+            script: Arc::new(Script::new(GCRA_SCRIPT)),
+        })
+    }
+
+    async fn check(&self, key: &str, limit: &RateLimit) -> Result<RateLimitOutcome> {
+        let wait_micros: u64 = self
+            .script
+            .key(format!("rate_limit:{}", key))
+            .arg(limit.refill_interval.as_micros() as u64)
+            .arg(limit.burst().as_micros() as u64)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .context("Failed to update the rate limit bucket in Redis")?;
+
+        Ok(match wait_micros {
+            0 => RateLimitOutcome::Allowed,
+            wait => RateLimitOutcome::Limited {
+                retry_after: Duration::from_micros(wait),
+            },
+        })
+    }
+}
+
+/// Same algorithm as the in-process store. Returns how many microseconds to wait, 0 if allowed.
+const GCRA_SCRIPT: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local interval = tonumber(ARGV[1])
+local burst = tonumber(ARGV[2])
+local tat = tonumber(redis.call('GET', KEYS[1])) or now
+if tat < now then
+    tat = now
+end
+local new_tat = tat + interval
+local wait = new_tat - now - burst
+if wait > 0 then
+    return wait
+end
+redis.call('SET', KEYS[1], new_tat, 'PX', math.ceil((new_tat - now) / 1000))
+return 0
+"#;
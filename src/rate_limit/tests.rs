@@ -0,0 +1,210 @@
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use config::{Config, FileFormat};
+
+use super::*;
+
+const LIMIT: RateLimit = RateLimit {
+    capacity: 3,
+    refill_interval: Duration::from_secs(10),
+};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn network(s: &str) -> IpNet {
+    s.parse().unwrap()
+}
+
+fn forwarded_for(values: &[&str]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for value in values {
+        headers.append(
+            HeaderName::from_static("x-forwarded-for"),
+            HeaderValue::from_str(value).unwrap(),
+        );
+    }
+    headers
+}
+
+#[test]
+fn a_full_bucket_allows_a_burst_of_capacity_requests() {
+    let store = InMemoryRateLimitStore::default();
+    let now = Instant::now();
+
+    for _ in 0..LIMIT.capacity {
+        assert_eq!(
+            store.check_at("key", &LIMIT, now),
+            RateLimitOutcome::Allowed
+        );
+    }
+    assert_eq!(
+        store.check_at("key", &LIMIT, now),
+        RateLimitOutcome::Limited {
+            retry_after: LIMIT.refill_interval
+        }
+    );
+}
+
+#[test]
+fn buckets_regain_one_request_per_refill_interval() {
+    let store = InMemoryRateLimitStore::default();
+    let now = Instant::now();
+    for _ in 0..LIMIT.capacity {
+        store.check_at("key", &LIMIT, now);
+    }
+
+    let later = now + Duration::from_secs(4);
+    assert_eq!(
+        store.check_at("key", &LIMIT, later),
+        RateLimitOutcome::Limited {
+            retry_after: Duration::from_secs(6)
+        }
+    );
+
+    let refilled = now + LIMIT.refill_interval;
+    assert_eq!(
+        store.check_at("key", &LIMIT, refilled),
+        RateLimitOutcome::Allowed
+    );
+    assert!(matches!(
+        store.check_at("key", &LIMIT, refilled),
+        RateLimitOutcome::Limited { .. }
+    ));
+}
+
+#[test]
+fn rejected_requests_do_not_drain_the_bucket() {
+    let store = InMemoryRateLimitStore::default();
+    let now = Instant::now();
+    for _ in 0..LIMIT.capacity + 5 {
+        store.check_at("key", &LIMIT, now);
+    }
+
+    assert_eq!(
+        store.check_at("key", &LIMIT, now + LIMIT.refill_interval),
+        RateLimitOutcome::Allowed
+    );
+}
+
+#[test]
+fn keys_have_independent_buckets() {
+    let store = InMemoryRateLimitStore::default();
+    let now = Instant::now();
+    for _ in 0..LIMIT.capacity {
+        store.check_at("a", &LIMIT, now);
+    }
+
+    assert_eq!(store.check_at("b", &LIMIT, now), RateLimitOutcome::Allowed);
+}
+
+#[test]
+fn forwarded_for_is_ignored_from_untrusted_peers() {
+    let headers = forwarded_for(&["203.0.113.7"]);
+
+    assert_eq!(
+        client_ip(ip("198.51.100.1"), &headers, &[]),
+        ip("198.51.100.1")
+    );
+}
+
+#[test]
+fn forwarded_for_is_used_from_trusted_proxies() {
+    let headers = forwarded_for(&["203.0.113.7"]);
+
+    assert_eq!(
+        client_ip(ip("10.0.0.1"), &headers, &[network("10.0.0.1/32")]),
+        ip("203.0.113.7")
+    );
+}
+
+#[test]
+fn proxies_are_trusted_anywhere_in_their_network() {
+    let trusted = [network("10.0.0.0/8")];
+    let headers = forwarded_for(&["203.0.113.7", "10.1.2.3"]);
+
+    assert_eq!(
+        client_ip(ip("10.200.0.1"), &headers, &trusted),
+        ip("203.0.113.7")
+    );
+    assert_eq!(
+        client_ip(ip("11.0.0.1"), &headers, &trusted),
+        ip("11.0.0.1")
+    );
+}
+
+#[test]
+fn spoofed_hops_before_the_first_untrusted_one_are_ignored() {
+    let trusted = [network("10.0.0.1/32"), network("10.0.0.2/32")];
+    // The client claims to be 192.0.2.1; the edge proxy appended the address it actually saw.
+    let headers = forwarded_for(&["192.0.2.1, 203.0.113.7", "10.0.0.2"]);
+
+    assert_eq!(
+        client_ip(ip("10.0.0.1"), &headers, &trusted),
+        ip("203.0.113.7")
+    );
+}
+
+#[test]
+fn malformed_hops_stop_the_walk() {
+    let trusted = [network("10.0.0.1/32"), network("10.0.0.2/32")];
+    let headers = forwarded_for(&["203.0.113.7, not-an-ip, 10.0.0.2"]);
+
+    assert_eq!(
+        client_ip(ip("10.0.0.1"), &headers, &trusted),
+        ip("10.0.0.2")
+    );
+}
+
+#[test]
+fn a_trusted_proxy_without_forwarded_for_is_the_client() {
+    assert_eq!(
+        client_ip(ip("10.0.0.1"), &HeaderMap::new(), &[network("10.0.0.1/32")]),
+        ip("10.0.0.1")
+    );
+}
+
+#[test]
+fn trusted_proxies_can_be_listed_or_separated_by_commas() {
+    for trusted_proxies in [
+        r#"["10.0.0.0/8", "192.168.1.1/32"]"#,
+        r#""10.0.0.0/8, 192.168.1.1/32""#,
+    ] {
+        let settings: RateLimitSettings = Config::builder()
+            .add_source(config::File::from_str(
+                &format!(
+                    r#"
+                    trusted_proxies: {}
+                    per_ip:
+                      capacity: 10
+                      refill_interval_seconds: 6
+                    per_email:
+                      capacity: 3
+                      refill_interval_seconds: 1200
+                    "#,
+                    trusted_proxies
+                ),
+                FileFormat::Yaml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        assert_eq!(
+            settings.trusted_proxies,
+            [network("10.0.0.0/8"), network("192.168.1.1/32")]
+        );
+    }
+}
+
+#[test]
+fn retry_after_is_rounded_up_to_whole_seconds() {
+    let response = too_many_requests("Slow down", Duration::from_millis(1500));
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "2");
+}
@@ -0,0 +1,126 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+
+struct FailedDelivery {
+    newsletter_issue_id: Uuid,
+    title: String,
+    subscriber_id: Uuid,
+    subscriber_email: String,
+    n_attempts: i16,
+    last_error: String,
+    failed_at: DateTime<Utc>,
+}
+
+pub async fn failed_deliveries(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let deliveries = match get_failed_deliveries(&pool).await {
+        Ok(deliveries) => deliveries,
+        Err(e) => {
+            error!(?e, "Failed to list the failed deliveries");
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let mut rows_html = String::new();
+    for d in &deliveries {
+        writeln!(
+            rows_html,
+            r#"        <tr>
+            <td>{title}</td>
+            <td>{email}</td>
+            <td>{n_attempts}</td>
+            <td>{failed_at}</td>
+            <td>{last_error}</td>
+            <td>
+                <form action="/admin/failed_deliveries" method="post">
+                    <input hidden type="text" name="newsletter_issue_id" value="{issue_id}">
+                    <input hidden type="text" name="subscriber_id" value="{subscriber_id}">
+                    <button type="submit">Re-queue</button>
+                </form>
+            </td>
+        </tr>"#,
+            title = htmlescape::encode_minimal(&d.title),
+            email = htmlescape::encode_minimal(&d.subscriber_email),
+            subscriber_id = d.subscriber_id,
+            n_attempts = d.n_attempts,
+            failed_at = d.failed_at.to_rfc3339(),
+            last_error = htmlescape::encode_minimal(&d.last_error),
+            issue_id = d.newsletter_issue_id,
+        )
+        .unwrap();
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Failed deliveries</title>
+</head>
+<body>
+    {msg_html}
+    <table>
+        <tr>
+            <th>Issue</th>
+            <th>Subscriber</th>
+            <th>Attempts</th>
+            <th>Failed at</th>
+            <th>Last error</th>
+            <th></th>
+        </tr>
+{rows_html}    </table>
+    <form action="/admin/failed_deliveries" method="post">
+        <button type="submit">Re-queue all</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        ))
+}
+
+async fn get_failed_deliveries(pool: &PgPool) -> Result<Vec<FailedDelivery>> {
+    let deliveries = sqlx::query_as!(
+        FailedDelivery,
+        r#"
+        SELECT
+            f.newsletter_issue_id,
+            i.title,
+            f.subscriber_id,
+            s.email AS subscriber_email,
+            f.n_attempts,
+            f.last_error,
+            f.failed_at
+        FROM failed_deliveries f
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        JOIN subscriptions s ON s.id = f.subscriber_id
+        ORDER BY f.failed_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the failed deliveries")?;
+
+    Ok(deliveries)
+}
@@ -0,0 +1,63 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::utils::see_other;
+
+#[derive(Deserialize)]
+pub struct RequeueFormData {
+    newsletter_issue_id: Option<Uuid>,
+    subscriber_id: Option<Uuid>,
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %*user_id))]
+pub async fn requeue_failed_deliveries(
+    form: web::Form<RequeueFormData>,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    match requeue(&form, &pool).await {
+        Ok(n_requeued) => {
+            FlashMessage::info(format!("Re-queued {} failed deliveries.", n_requeued)).send();
+            see_other("/admin/failed_deliveries")
+        }
+        Err(e) => {
+            error!(?e, "Failed to re-queue the failed deliveries");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn requeue(form: &RequeueFormData, pool: &PgPool) -> Result<u64> {
+    let mut transaction = pool.begin().await?;
+
+    let n_requeued = sqlx::query!(
+        r#"
+        WITH requeued AS (
+            DELETE FROM failed_deliveries
+            WHERE ($1::uuid IS NULL OR newsletter_issue_id = $1)
+              AND ($2::uuid IS NULL OR subscriber_id = $2)
+            RETURNING newsletter_issue_id, subscriber_id
+        )
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_id)
+        SELECT newsletter_issue_id, subscriber_id
+        FROM requeued
+        ON CONFLICT DO NOTHING
+        "#,
+        form.newsletter_issue_id,
+        form.subscriber_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to re-queue the failed deliveries")?
+    .rows_affected();
+
+    transaction.commit().await?;
+
+    Ok(n_requeued)
+}
@@ -0,0 +1,11 @@
+pub use dashboard::*;
+pub use failed_deliveries::*;
+pub use logout::*;
+pub use newsletters::*;
+pub use password::*;
+
+mod dashboard;
+mod failed_deliveries;
+mod logout;
+mod newsletters;
+mod password;
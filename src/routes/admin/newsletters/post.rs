@@ -0,0 +1,90 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Result;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{error, warn};
+
+use crate::authentication::UserId;
+use crate::domain::IdempotencyKey;
+use crate::idempotency::{save_response, try_processing, NextAction};
+use crate::routes::newsletters::enqueue_issue;
+use crate::utils::see_other;
+
+#[derive(Deserialize)]
+pub struct PublishIssueFormData {
+    title: String,
+    #[serde(default)]
+    text_content: String,
+    html_content: String,
+    idempotency_key: String,
+}
+
+#[tracing::instrument(skip_all, fields(title = %form.title, user_id = %*user_id))]
+pub async fn publish_issue(
+    form: web::Form<PublishIssueFormData>,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let PublishIssueFormData {
+        title,
+        text_content,
+        html_content,
+        idempotency_key,
+    } = form.0;
+
+    let idempotency_key = match IdempotencyKey::try_from(idempotency_key) {
+        Ok(idempotency_key) => idempotency_key,
+        Err(e) => {
+            warn!(?e, "Rejected an invalid idempotency key");
+            return HttpResponse::BadRequest().finish();
+        }
+    };
+
+    // An empty text area means the plain-text body should be generated.
+    let text_content = Some(text_content.as_str()).filter(|t| !t.trim().is_empty());
+
+    let result = publish_issue_inner(
+        &title,
+        &html_content,
+        text_content,
+        &idempotency_key,
+        user_id.into_inner(),
+        &pool,
+    )
+    .await;
+
+    result.unwrap_or_else(|e| {
+        error!(?e, "Failed to publish the newsletter issue");
+        HttpResponse::InternalServerError().finish()
+    })
+}
+
+async fn publish_issue_inner(
+    title: &str,
+    html_content: &str,
+    text_content: Option<&str>,
+    idempotency_key: &IdempotencyKey,
+    user_id: UserId,
+    pool: &PgPool,
+) -> Result<HttpResponse> {
+    let mut transaction = match try_processing(pool, idempotency_key, user_id).await? {
+        NextAction::StartProcessing(transaction) => *transaction,
+        NextAction::ReturnSavedResponse(saved_response) => {
+            success_message().send();
+            return Ok(saved_response);
+        }
+    };
+
+    enqueue_issue(&mut transaction, title, html_content, text_content).await?;
+
+    let response = see_other("/admin/newsletters");
+    let response = save_response(transaction, idempotency_key, user_id, response).await?;
+    success_message().send();
+
+    Ok(response)
+}
+
+fn success_message() -> FlashMessage {
+    FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
+}
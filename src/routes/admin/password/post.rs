@@ -0,0 +1,81 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::error;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::authentication::{get_username, validate_credentials, AuthError, Credentials, UserId};
+use crate::utils::see_other;
+
+const MIN_PASSWORD_LENGTH: usize = 12;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+#[derive(Deserialize)]
+pub struct ChangePasswordFormData {
+    current_password: Secret<String>,
+    new_password: Secret<String>,
+    new_password_check: Secret<String>,
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %*user_id))]
+pub async fn change_password(
+    form: web::Form<ChangePasswordFormData>,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+
+    if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
+        FlashMessage::error(
+            "You entered two different new passwords - the field values must match.",
+        )
+        .send();
+        return see_other("/admin/password");
+    }
+
+    let new_password_length = form.new_password.expose_secret().graphemes(true).count();
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&new_password_length) {
+        FlashMessage::error(format!(
+            "The new password must be between {} and {} characters long.",
+            MIN_PASSWORD_LENGTH, MAX_PASSWORD_LENGTH
+        ))
+        .send();
+        return see_other("/admin/password");
+    }
+
+    let username = match get_username(user_id, &pool).await {
+        Ok(username) => username,
+        Err(e) => {
+            error!(?e, "Failed to change the password");
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let credentials = Credentials {
+        username,
+        password: form.0.current_password,
+    };
+    match validate_credentials(credentials, &pool).await {
+        Ok(_) => {}
+        Err(AuthError::InvalidCredentials(_)) => {
+            FlashMessage::error("The current password is incorrect.").send();
+            return see_other("/admin/password");
+        }
+        Err(AuthError::UnexpectedError(e)) => {
+            error!(?e, "Failed to change the password");
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    if let Err(e) =
+        crate::authentication::change_password(user_id, form.0.new_password, &pool).await
+    {
+        error!(?e, "Failed to change the password");
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    FlashMessage::info("Your password has been changed.").send();
+    see_other("/admin/password")
+}
@@ -1,5 +1,126 @@
-use actix_web::HttpResponse;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::time::Duration;
+
+use actix_web::{web, HttpResponse};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use sqlx::migrate::Migrator;
+use sqlx::PgPool;
+
+use crate::configuration::HealthSettings;
+use crate::email_client::EmailClient;
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
 pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().finish()
 }
+
+/// The process is up and serving requests. Says nothing about its dependencies.
+pub async fn liveness() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    status: &'static str,
+    components: BTreeMap<&'static str, ComponentHealth>,
+}
+
+#[derive(Serialize)]
+struct ComponentHealth {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+/// Checks every dependency concurrently, answering `503` if any of them is down.
+#[tracing::instrument(skip_all)]
+pub async fn readiness(
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    settings: web::Data<HealthSettings>,
+) -> HttpResponse {
+    let timeout = settings.timeout();
+    let (database, migrations, email) = tokio::join!(
+        check(timeout, check_database(&pool)),
+        check(timeout, check_migrations(&pool)),
+        async {
+            if settings.check_email_provider {
+                Some(check(timeout, email_client.ping()).await)
+            } else {
+                None
+            }
+        }
+    );
+
+    let mut components = BTreeMap::from([("database", database), ("migrations", migrations)]);
+    if let Some(email) = email {
+        components.insert("email", email);
+    }
+
+    let ready = components
+        .values()
+        .all(|component| component.status == "up");
+    let mut response = if ready {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    response.json(Readiness {
+        status: if ready { "ready" } else { "degraded" },
+        components,
+    })
+}
+
+async fn check(timeout: Duration, probe: impl Future<Output = Result<()>>) -> ComponentHealth {
+    let outcome = match tokio::time::timeout(timeout, probe).await {
+        Ok(outcome) => outcome,
+        Err(_) => Err(anyhow::anyhow!("Timed out after {}ms", timeout.as_millis())),
+    };
+
+    match outcome {
+        Ok(()) => ComponentHealth {
+            status: "up",
+            detail: None,
+        },
+        Err(e) => {
+            tracing::warn!(error = ?e, "Readiness check failed");
+            ComponentHealth {
+                status: "down",
+                detail: Some(format!("{:#}", e)),
+            }
+        }
+    }
+}
+
+async fn check_database(pool: &PgPool) -> Result<()> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
+/// Compares the migrations embedded in the binary with those applied to the database.
+async fn check_migrations(pool: &PgPool) -> Result<()> {
+    // `_sqlx_migrations` is created by the migrator at runtime, so it cannot be checked at
+    // compile time.
+    let applied: HashMap<i64, bool> =
+        sqlx::query_as::<_, (i64, bool)>("SELECT version, success FROM _sqlx_migrations")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect();
+
+    for migration in MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+    {
+        match applied.get(&migration.version) {
+            Some(true) => {}
+            Some(false) => bail!("Migration {} failed to apply", migration.version),
+            None => bail!("Migration {} has not been applied", migration.version),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use secrecy::Secret;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{error, warn};
+
+use crate::authentication::{validate_credentials, AuthError, Credentials};
+use crate::session::TypedSession;
+use crate::utils::see_other;
+
+#[derive(Deserialize)]
+pub struct LoginFormData {
+    username: String,
+    password: Secret<String>,
+}
+
+#[tracing::instrument(skip_all, fields(username = %form.username, user_id = tracing::field::Empty))]
+pub async fn login(
+    form: web::Form<LoginFormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+) -> HttpResponse {
+    let credentials = Credentials {
+        username: form.0.username,
+        password: form.0.password,
+    };
+
+    let user_id = match validate_credentials(credentials, &pool).await {
+        Ok(user_id) => user_id,
+        Err(AuthError::InvalidCredentials(e)) => {
+            warn!(?e, "Rejected login attempt");
+            FlashMessage::error("Authentication failed").send();
+            return see_other("/login");
+        }
+        Err(AuthError::UnexpectedError(e)) => {
+            error!(?e, "Failed to log in");
+            FlashMessage::error("Something went wrong. Please try again").send();
+            return see_other("/login");
+        }
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+
+    session.renew();
+    if let Err(e) = session
+        .insert_user_id(user_id)
+        .context("Failed to store the user id in the session")
+    {
+        error!(?e);
+        FlashMessage::error("Something went wrong. Please try again").send();
+        return see_other("/login");
+    }
+
+    see_other("/admin/dashboard")
+}
@@ -0,0 +1,12 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::metrics::{encode, record_pool_state};
+
+pub async fn metrics(pool: web::Data<PgPool>) -> HttpResponse {
+    record_pool_state(&pool);
+
+    HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(encode())
+}
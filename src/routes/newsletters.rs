@@ -0,0 +1,170 @@
+use actix_web::http::header::HeaderMap;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::authentication::{BasicAuthUser, UserId};
+use crate::domain::IdempotencyKey;
+use crate::email_preparation::{html_to_text, inline_css};
+use crate::idempotency::{save_response, try_processing, NextAction};
+
+#[derive(Deserialize, Debug)]
+pub struct BodyData {
+    title: String,
+    content: Content,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Content {
+    html: String,
+    /// Generated from `html` when omitted.
+    text: Option<String>,
+}
+
+#[tracing::instrument(skip_all, fields(title = %body.title, user_id = %user.user_id()))]
+pub async fn publish_newsletter(
+    user: BasicAuthUser,
+    request: HttpRequest,
+    body: web::Json<BodyData>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let idempotency_key = match get_idempotency_key(request.headers()) {
+        Ok(idempotency_key) => idempotency_key,
+        Err(e) => {
+            warn!(?e, "Rejected an invalid idempotency key");
+            return HttpResponse::BadRequest().finish();
+        }
+    };
+
+    publish_newsletter_inner(&body, idempotency_key.as_ref(), user.user_id(), &pool)
+        .await
+        .unwrap_or_else(|e| {
+            error!(?e, "Failed to publish the newsletter issue");
+            HttpResponse::InternalServerError().finish()
+        })
+}
+
+async fn publish_newsletter_inner(
+    body: &BodyData,
+    idempotency_key: Option<&IdempotencyKey>,
+    user_id: UserId,
+    pool: &PgPool,
+) -> Result<HttpResponse> {
+    let mut transaction = match idempotency_key {
+        Some(idempotency_key) => match try_processing(pool, idempotency_key, user_id).await? {
+            NextAction::StartProcessing(transaction) => *transaction,
+            NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+        },
+        None => pool.begin().await?,
+    };
+
+    enqueue_issue(
+        &mut transaction,
+        &body.title,
+        &body.content.html,
+        body.content.text.as_deref(),
+    )
+    .await?;
+
+    let response = HttpResponse::Accepted().finish();
+    match idempotency_key {
+        Some(idempotency_key) => {
+            save_response(transaction, idempotency_key, user_id, response).await
+        }
+        None => {
+            transaction.commit().await?;
+            Ok(response)
+        }
+    }
+}
+
+fn get_idempotency_key(headers: &HeaderMap) -> Result<Option<IdempotencyKey>> {
+    let Some(header_value) = headers.get("Idempotency-Key") else {
+        return Ok(None);
+    };
+
+    let idempotency_key = header_value
+        .to_str()
+        .context("The 'Idempotency-Key' header was not a valid UTF8 string")?
+        .to_string()
+        .try_into()?;
+
+    Ok(Some(idempotency_key))
+}
+
+/// Stores an issue and queues it for every confirmed subscriber. Without `text_content`, the
+/// plain-text body is generated from the HTML.
+///
+/// The HTML is stored with its CSS inlined, so that a stylesheet the inliner cannot handle fails
+/// the publication instead of every delivery.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn enqueue_issue(
+    transaction: &mut Transaction<'static, Postgres>,
+    title: &str,
+    html_content: &str,
+    text_content: Option<&str>,
+) -> Result<Uuid> {
+    let text_content = match text_content {
+        Some(text_content) => text_content.to_string(),
+        None => html_to_text(html_content)?,
+    };
+    let html_content = inline_css(html_content)?;
+    let newsletter_issue_id =
+        insert_newsletter_issue(transaction, title, &html_content, &text_content).await?;
+
+    enqueue_delivery_tasks(transaction, newsletter_issue_id).await?;
+
+    Ok(newsletter_issue_id)
+}
+
+#[tracing::instrument(skip_all)]
+async fn insert_newsletter_issue(
+    transaction: &mut Transaction<'static, Postgres>,
+    title: &str,
+    html_content: &str,
+    text_content: &str,
+) -> Result<Uuid> {
+    let newsletter_issue_id = Uuid::new_v4();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id, title, text_content, html_content, published_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        "#,
+        newsletter_issue_id,
+        title,
+        text_content,
+        html_content,
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to store the newsletter issue")?;
+
+    Ok(newsletter_issue_id)
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'static, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_id)
+        SELECT $1, id
+        FROM subscriptions
+        WHERE status = 'confirmed'
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to enqueue the delivery tasks")?;
+
+    Ok(())
+}
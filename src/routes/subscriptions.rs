@@ -1,53 +1,240 @@
-use actix_web::{web, HttpResponse};
+use std::future::{ready, Future};
+use std::pin::Pin;
+use std::time::Duration;
+
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
 use anyhow::{Context, Result};
 use chrono::Utc;
 use rand::{thread_rng, Rng};
-use sqlx::PgPool;
-use tracing::error;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::info;
 use uuid::Uuid;
 
-use crate::domain::NewSubscriber;
+use crate::domain::{
+    DisposableDomains, FieldError, NewSubscriber, PopularDomains, ValidationError,
+};
+use crate::domain_verification::{DomainStatus, DomainVerification};
 use crate::email_client::EmailClient;
-use crate::startup::ApplicationBaseUrl;
+use crate::email_templates::{ConfirmationEmail, EmailTemplates};
+use crate::rate_limit::{too_many_requests, RateLimitOutcome, RateLimiter};
+use crate::startup::{ApplicationBaseUrl, SubscriptionTokenExpiry};
+use crate::utils::{error_chain_fmt, ProblemDetails, ResponseFormat};
 
+/// A subscription request, sent either as JSON or as a URL-encoded form.
+///
+/// Missing fields are read as empty, so that they are reported along with the invalid ones.
 #[derive(serde::Deserialize, Debug)]
 pub struct SubscribeFormData {
+    #[serde(default)]
     pub email: String,
+    #[serde(default)]
     pub name: String,
 }
 
-#[tracing::instrument(skip(pg_pool, email_client, base_url))]
+impl FromRequest for SubscribeFormData {
+    type Error = SubscribeError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let invalid_body = |e: actix_web::Error| SubscribeError::InvalidBody(e.to_string());
+        match req.content_type() {
+            "application/json" => {
+                let json = web::Json::<Self>::from_request(req, payload);
+                Box::pin(async move { Ok(json.await.map_err(invalid_body)?.into_inner()) })
+            }
+            "application/x-www-form-urlencoded" => {
+                let form = web::Form::<Self>::from_request(req, payload);
+                Box::pin(async move { Ok(form.await.map_err(invalid_body)?.into_inner()) })
+            }
+            _ => Box::pin(ready(Err(SubscribeError::UnsupportedMediaType))),
+        }
+    }
+}
+
+// Every argument is an extractor.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(
+    pg_pool,
+    email_client,
+    email_templates,
+    base_url,
+    token_expiry,
+    rate_limiter,
+    disposable_domains,
+    domain_verification,
+    popular_domains
+))]
 pub async fn subscribe(
-    form: web::Form<SubscribeFormData>,
+    form: SubscribeFormData,
+    format: ResponseFormat,
     pg_pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
+    email_templates: web::Data<EmailTemplates>,
     base_url: web::Data<ApplicationBaseUrl>,
-) -> HttpResponse {
-    let Ok(new_subscriber) = NewSubscriber::try_from(form.0) else {
-        return HttpResponse::BadRequest().finish();
-    };
+    token_expiry: web::Data<SubscriptionTokenExpiry>,
+    rate_limiter: web::Data<RateLimiter>,
+    disposable_domains: web::Data<DisposableDomains>,
+    domain_verification: web::Data<DomainVerification>,
+    popular_domains: web::Data<PopularDomains>,
+) -> Result<HttpResponse, SubscribeError> {
+    let new_subscriber = NewSubscriber::parse(form, &disposable_domains, &popular_domains)
+        .map_err(|errors| SubscribeError::ValidationError { errors, format })?;
 
-    if let Err(e) = subscribe_internal(new_subscriber, &pg_pool, &email_client, &base_url.0).await {
-        error!(?e, "Failed to store new subscriber");
-        return HttpResponse::InternalServerError().finish();
+    if let RateLimitOutcome::Limited { retry_after } = rate_limiter
+        .check_email(new_subscriber.email.canonical())
+        .await
+    {
+        return Err(SubscribeError::TooManyRequests { retry_after });
     }
 
-    HttpResponse::Ok().finish()
+    subscribe_internal(
+        new_subscriber,
+        format,
+        &domain_verification,
+        &popular_domains,
+        &pg_pool,
+        &email_client,
+        &email_templates,
+        &base_url.0,
+        token_expiry.0,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
 }
 
+#[derive(thiserror::Error)]
+pub enum SubscribeError {
+    #[error(
+        "Subscriptions must be sent as application/json or application/x-www-form-urlencoded."
+    )]
+    UnsupportedMediaType,
+    #[error("{0}")]
+    InvalidBody(String),
+    /// Rendered in the format negotiated from the request's `Accept` header.
+    #[error("The subscription request is invalid.")]
+    ValidationError {
+        errors: Vec<FieldError>,
+        format: ResponseFormat,
+    },
+    #[error("Too many subscription requests for this email address. Please try again later.")]
+    TooManyRequests { retry_after: Duration },
+    #[error("The domain of this email address could not be verified. Please try again later.")]
+    DomainVerificationError(#[source] anyhow::Error),
+    #[error("Failed to store the new subscriber.")]
+    StoreError(#[source] anyhow::Error),
+    #[error("Failed to send the confirmation email.")]
+    SendEmailError(#[source] anyhow::Error),
+}
+
+impl std::fmt::Debug for SubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for SubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            SubscribeError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            SubscribeError::InvalidBody(_) | SubscribeError::ValidationError { .. } => {
+                StatusCode::BAD_REQUEST
+            }
+            SubscribeError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            SubscribeError::DomainVerificationError(_) => StatusCode::SERVICE_UNAVAILABLE,
+            SubscribeError::StoreError(_) | SubscribeError::SendEmailError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            SubscribeError::TooManyRequests { retry_after } => {
+                return too_many_requests(&self.to_string(), *retry_after);
+            }
+            SubscribeError::ValidationError { errors, format } => {
+                return ProblemDetails::new(self.status_code(), Some(self.to_string()))
+                    .with_errors(errors.clone())
+                    .into_response_as(*format);
+            }
+            _ => {}
+        }
+        // Server-side failures are logged in full; the body only tells the client what went wrong.
+        ProblemDetails::new(self.status_code(), Some(self.to_string())).into_response()
+    }
+}
+
+// Every argument is needed by one of the steps.
+#[allow(clippy::too_many_arguments)]
 async fn subscribe_internal(
     new_subscriber: NewSubscriber,
+    format: ResponseFormat,
+    domain_verification: &DomainVerification,
+    popular_domains: &PopularDomains,
     pg_pool: &PgPool,
     email_client: &EmailClient,
+    email_templates: &EmailTemplates,
     base_url: &str,
-) -> Result<()> {
-    let subscriber_uuid = insert_subscriber(&new_subscriber, pg_pool).await?;
+    token_expiry: chrono::Duration,
+) -> Result<(), SubscribeError> {
+    // Checked after the rate limits, as it may take a DNS lookup.
+    let domain = new_subscriber.email.domain();
+    match domain_verification
+        .verify(domain)
+        .await
+        .map_err(SubscribeError::DomainVerificationError)?
+    {
+        DomainStatus::Deliverable => {}
+        DomainStatus::Undeliverable => {
+            let error = ValidationError::UndeliverableDomain {
+                domain: domain.to_owned(),
+            };
+            let suggestion = popular_domains.suggest(new_subscriber.email.as_ref());
+            return Err(SubscribeError::ValidationError {
+                errors: vec![FieldError::new("email", error).with_suggestion(suggestion)],
+                format,
+            });
+        }
+    }
+
+    let mut transaction = pg_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(SubscribeError::StoreError)?;
+
+    let Some(subscriber_uuid) = upsert_subscriber(&mut transaction, &new_subscriber)
+        .await
+        .map_err(SubscribeError::StoreError)?
+    else {
+        info!("The subscriber has already confirmed their subscription");
+        return Ok(());
+    };
 
     let token = generate_subscription_token();
 
-    store_token(pg_pool, subscriber_uuid, &token).await?;
+    store_token(&mut transaction, subscriber_uuid, &token, token_expiry)
+        .await
+        .map_err(SubscribeError::StoreError)?;
 
-    send_confirmation_email(email_client, new_subscriber, base_url, &token).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the transaction to store a new subscriber")
+        .map_err(SubscribeError::StoreError)?;
+
+    send_confirmation_email(
+        email_client,
+        email_templates,
+        new_subscriber,
+        base_url,
+        &token,
+    )
+    .await
+    .map_err(SubscribeError::SendEmailError)?;
 
     Ok(())
 }
@@ -60,13 +247,24 @@ fn generate_subscription_token() -> String {
         .collect()
 }
 
-async fn store_token(pg_pool: &PgPool, subscriber_uuid: Uuid, token: &String) -> Result<()> {
+async fn store_token(
+    transaction: &mut Transaction<'static, Postgres>,
+    subscriber_uuid: Uuid,
+    token: &str,
+    token_expiry: chrono::Duration,
+) -> Result<()> {
+    let created_at = Utc::now();
     sqlx::query!(
-        r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id) VALUES ($1, $2)"#,
+        r#"
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
         token,
-        subscriber_uuid
+        subscriber_uuid,
+        created_at,
+        created_at + token_expiry,
     )
-    .execute(pg_pool)
+    .execute(&mut **transaction)
     .await
     .context("Failed to store subscription token")?;
 
@@ -74,27 +272,43 @@ async fn store_token(pg_pool: &PgPool, subscriber_uuid: Uuid, token: &String) ->
 }
 
 #[tracing::instrument(skip_all)]
-async fn insert_subscriber(new_subscriber: &NewSubscriber, pg_pool: &PgPool) -> Result<Uuid> {
-    let subscriber_uuid = Uuid::new_v4();
-    sqlx::query!(
+/// Stores a pending subscriber, returning `None` if they have already confirmed.
+///
+/// Pending and unsubscribed rows go (back) through confirmation under their existing id. Addresses
+/// are matched by their canonical form, and the row keeps the latest spelling.
+async fn upsert_subscriber(
+    transaction: &mut Transaction<'static, Postgres>,
+    new_subscriber: &NewSubscriber,
+) -> Result<Option<Uuid>> {
+    let subscriber = sqlx::query!(
         r#"
-        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        INSERT INTO subscriptions (id, email, canonical_email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, $4, $5, 'pending_confirmation')
+        ON CONFLICT (canonical_email) DO UPDATE
+        SET email = EXCLUDED.email,
+            name = EXCLUDED.name,
+            subscribed_at = EXCLUDED.subscribed_at,
+            status = EXCLUDED.status
+        WHERE subscriptions.status <> 'confirmed'
+        RETURNING id
         "#,
-        subscriber_uuid,
+        Uuid::new_v4(),
         new_subscriber.email.as_ref(),
+        new_subscriber.email.canonical(),
         new_subscriber.name.as_ref(),
         Utc::now(),
     )
-    .execute(pg_pool)
-    .await?;
+    .fetch_optional(&mut **transaction)
+    .await
+    .context("Failed to store the subscriber")?;
 
-    Ok(subscriber_uuid)
+    Ok(subscriber.map(|s| s.id))
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn send_confirmation_email(
     email_client: &EmailClient,
+    email_templates: &EmailTemplates,
     new_subscriber: NewSubscriber,
     base_url: &str,
     token: &str,
@@ -103,20 +317,17 @@ pub async fn send_confirmation_email(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, token
     );
+    let body = email_templates.render(&ConfirmationEmail {
+        subscriber_name: new_subscriber.name.as_ref(),
+        confirmation_link: &confirmation_link,
+    })?;
 
     email_client
         .send_email(
             new_subscriber.email.as_ref(),
             "Welcome!",
-            &format!(
-                "Welcome to our newsletter!<br />\
-                Click <a href=\"{}\">here</a> to confirm your subscription.",
-                confirmation_link
-            ),
-            &format!(
-                "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
-                confirmation_link
-            ),
+            &body.html,
+            &body.text,
         )
         .await
 }
@@ -1,61 +1,149 @@
-use actix_web::{web, HttpResponse};
-use anyhow::{anyhow, bail, Context, Result};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
-use sqlx::PgPool;
-use tracing::error;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::info;
 use uuid::Uuid;
 
+use crate::utils::{error_chain_fmt, ProblemDetails};
+
 #[derive(Deserialize, Debug)]
 pub struct Parameters {
     subscription_token: String,
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn confirm(parameters: web::Query<Parameters>, pool: web::Data<PgPool>) -> HttpResponse {
-    confirm_inner(&parameters.subscription_token, &pool)
+pub async fn confirm(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ConfirmError> {
+    let mut transaction = pool
+        .begin()
         .await
-        .unwrap_or_else(|e| {
-            error!(?e);
-            HttpResponse::InternalServerError().finish()
-        })
-}
+        .context("Failed to acquire a Postgres connection from the pool")?;
 
-async fn confirm_inner(subscription_token: &str, pool: &PgPool) -> Result<HttpResponse> {
-    let id = get_subscriber_id_from_token(subscription_token, pool)
+    let Some(token) = get_token(&mut transaction, &parameters.subscription_token)
         .await
-        .context("Failed to retrieve subscriber ID from the database")?;
+        .context("Failed to retrieve the subscription token from the database")?
+    else {
+        return Err(ConfirmError::UnknownToken);
+    };
+
+    // Following the same link twice is fine, whether or not it has expired since.
+    if token.consumed_at.is_some() {
+        info!("The subscription token has already been used");
+        return Ok(HttpResponse::Ok().finish());
+    }
 
-    confirm_subscriber(id, pool)
+    if token.expires_at <= Utc::now() {
+        return Err(ConfirmError::ExpiredToken);
+    }
+
+    confirm_subscriber(&mut transaction, token.subscriber_id)
         .await
         .context("Failed to confirm the subscriber in the database")?;
 
+    consume_token(&mut transaction, &parameters.subscription_token)
+        .await
+        .context("Failed to mark the subscription token as used")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the transaction to confirm a subscriber")?;
+
     Ok(HttpResponse::Ok().finish())
 }
 
-async fn get_subscriber_id_from_token(subscription_token: &str, pool: &PgPool) -> Result<Uuid> {
-    let result = sqlx::query!(
-        r#"SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1"#,
+#[derive(thiserror::Error)]
+pub enum ConfirmError {
+    #[error("The subscription token is unknown.")]
+    UnknownToken,
+    #[error("The subscription token has expired. Please subscribe again.")]
+    ExpiredToken,
+    #[error("Failed to confirm the subscription.")]
+    StoreError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ConfirmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ConfirmError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ConfirmError::UnknownToken => StatusCode::UNAUTHORIZED,
+            ConfirmError::ExpiredToken => StatusCode::GONE,
+            ConfirmError::StoreError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        ProblemDetails::new(self.status_code(), Some(self.to_string())).into_response()
+    }
+}
+
+struct SubscriptionToken {
+    subscriber_id: Uuid,
+    expires_at: DateTime<Utc>,
+    consumed_at: Option<DateTime<Utc>>,
+}
+
+async fn get_token(
+    transaction: &mut Transaction<'static, Postgres>,
+    subscription_token: &str,
+) -> Result<Option<SubscriptionToken>> {
+    let token = sqlx::query_as!(
+        SubscriptionToken,
+        r#"
+        SELECT subscriber_id, expires_at, consumed_at
+        FROM subscription_tokens
+        WHERE subscription_token = $1
+        FOR UPDATE
+        "#,
         subscription_token
     )
-    .fetch_one(pool)
+    .fetch_optional(&mut **transaction)
     .await
     .context("Failed to query the database")?;
 
-    Ok(result.subscriber_id)
+    Ok(token)
 }
 
-async fn confirm_subscriber(subscriber_id: Uuid, pool: &PgPool) -> Result<()> {
-    let result = sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
+async fn confirm_subscriber(
+    transaction: &mut Transaction<'static, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<()> {
+    // Leaves unsubscribed rows alone: a stale link must not sign somebody back up.
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'confirmed'
+        WHERE id = $1 AND status = 'pending_confirmation'
+        "#,
         subscriber_id
     )
-    .execute(pool)
+    .execute(&mut **transaction)
     .await
     .context("Failed to update the subscriber status in the database")?;
 
-    match result.rows_affected() {
-        0 => Err(anyhow!("No subscriber found with that ID")),
-        1 => Ok(()),
-        n => bail!("Updated more than one row: {}", n),
-    }
+    Ok(())
+}
+
+async fn consume_token(
+    transaction: &mut Transaction<'static, Postgres>,
+    subscription_token: &str,
+) -> Result<()> {
+    sqlx::query!(
+        r#"UPDATE subscription_tokens SET consumed_at = now() WHERE subscription_token = $1"#,
+        subscription_token
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
 }
@@ -0,0 +1,109 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::unsubscribe::UnsubscribeLinks;
+
+#[derive(Deserialize, Debug)]
+pub struct UnsubscribeParameters {
+    token: String,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn unsubscribe_form(
+    parameters: web::Query<UnsubscribeParameters>,
+    unsubscribe_links: web::Data<UnsubscribeLinks>,
+) -> HttpResponse {
+    if let Err(e) = unsubscribe_links.verify(&parameters.token) {
+        warn!(?e, "Rejected an invalid unsubscribe token");
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    // Mail scanners follow links: leaving the newsletter requires submitting the form.
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+</head>
+<body>
+    <p>Do you want to stop receiving our newsletter?</p>
+    <form action="/subscriptions/unsubscribe?token={}" method="post">
+        <button type="submit">Unsubscribe</button>
+    </form>
+</body>
+</html>"#,
+            htmlescape::encode_minimal(&parameters.token)
+        ))
+}
+
+/// Handles both the confirmation form and RFC 8058 one-click requests.
+#[tracing::instrument(skip_all, fields(subscriber_id = tracing::field::Empty))]
+pub async fn unsubscribe(
+    parameters: web::Query<UnsubscribeParameters>,
+    pool: web::Data<PgPool>,
+    unsubscribe_links: web::Data<UnsubscribeLinks>,
+) -> HttpResponse {
+    let subscriber_id = match unsubscribe_links.verify(&parameters.token) {
+        Ok(subscriber_id) => subscriber_id,
+        Err(e) => {
+            warn!(?e, "Rejected an invalid unsubscribe token");
+            return HttpResponse::Unauthorized().finish();
+        }
+    };
+    tracing::Span::current().record("subscriber_id", tracing::field::display(subscriber_id));
+
+    if let Err(e) = unsubscribe_subscriber(subscriber_id, &pool).await {
+        error!(?e, "Failed to unsubscribe the subscriber");
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribed</title>
+</head>
+<body>
+    <p>You have been unsubscribed. You will not receive further issues.</p>
+</body>
+</html>"#,
+    )
+}
+
+async fn unsubscribe_subscriber(subscriber_id: Uuid, pool: &PgPool) -> Result<()> {
+    let mut transaction = pool.begin().await?;
+
+    // Unknown ids are fine: the link stays valid after the subscriber row goes away.
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'unsubscribed'
+        WHERE id = $1
+        "#,
+        subscriber_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to update the subscriber status in the database")?;
+
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_id = $1"#,
+        subscriber_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to drop the pending deliveries")?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::future::{ready, Ready};
+
+use actix_session::{Session, SessionExt, SessionGetError, SessionInsertError};
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpRequest};
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+
+pub use store::*;
+
+mod store;
+
+pub struct TypedSession(Session);
+
+impl TypedSession {
+    const USER_ID_KEY: &'static str = "user_id";
+
+    pub fn renew(&self) {
+        self.0.renew();
+    }
+
+    pub fn insert_user_id(&self, user_id: UserId) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::USER_ID_KEY, user_id.as_ref())
+    }
+
+    pub fn get_user_id(&self) -> Result<Option<UserId>, SessionGetError> {
+        Ok(self.0.get::<Uuid>(Self::USER_ID_KEY)?.map(UserId::from))
+    }
+
+    pub fn log_out(self) {
+        self.0.purge()
+    }
+}
+
+impl FromRequest for TypedSession {
+    type Error = <Session as FromRequest>::Error;
+    type Future = Ready<Result<TypedSession, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(TypedSession(req.get_session())))
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use actix_session::storage::{
+    LoadError, RedisSessionStore, SaveError, SessionKey, SessionStore, UpdateError,
+};
+use actix_web::cookie::time::Duration;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use tracing::warn;
+
+type SessionState = HashMap<String, String>;
+
+#[derive(Clone)]
+pub enum SessionStoreBackend {
+    Redis(RedisSessionStore),
+    Postgres(PgSessionStore),
+}
+
+impl SessionStoreBackend {
+    pub async fn new(redis_uri: Option<&Secret<String>>, pool: PgPool) -> Result<Self> {
+        let store = match redis_uri {
+            Some(redis_uri) => Self::Redis(
+                RedisSessionStore::new(redis_uri.expose_secret())
+                    .await
+                    .context("Failed to connect to the Redis session store")?,
+            ),
+            None => Self::Postgres(PgSessionStore::new(pool)),
+        };
+
+        Ok(store)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for SessionStoreBackend {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        match self {
+            Self::Redis(store) => store.load(session_key).await,
+            Self::Postgres(store) => store.load(session_key).await,
+        }
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        match self {
+            Self::Redis(store) => store.save(session_state, ttl).await,
+            Self::Postgres(store) => store.save(session_state, ttl).await,
+        }
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        match self {
+            Self::Redis(store) => store.update(session_key, session_state, ttl).await,
+            Self::Postgres(store) => store.update(session_key, session_state, ttl).await,
+        }
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> Result<()> {
+        match self {
+            Self::Redis(store) => store.update_ttl(session_key, ttl).await,
+            Self::Postgres(store) => store.update_ttl(session_key, ttl).await,
+        }
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<()> {
+        match self {
+            Self::Redis(store) => store.delete(session_key).await,
+            Self::Postgres(store) => store.delete(session_key).await,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgSessionStore {
+    pool: PgPool,
+}
+
+impl PgSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn insert(
+        &self,
+        session_key: &SessionKey,
+        session_state: &serde_json::Value,
+        ttl: &Duration,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO sessions (session_key, session_state, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+            session_key.as_ref(),
+            session_state,
+            expires_at(ttl),
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert session state")?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Reads already ignore expired sessions; this keeps them from piling up.
+    async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query!(r#"DELETE FROM sessions WHERE expires_at <= now()"#)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete expired sessions")?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for PgSessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let row = sqlx::query!(
+            r#"SELECT session_state FROM sessions WHERE session_key = $1 AND expires_at > now()"#,
+            session_key.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to load session state")
+        .map_err(LoadError::Other)?;
+
+        row.map(|row| serde_json::from_value(row.session_state))
+            .transpose()
+            .context("Failed to deserialize session state")
+            .map_err(LoadError::Deserialization)
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let session_state = serde_json::to_value(session_state)
+            .context("Failed to serialize session state")
+            .map_err(SaveError::Serialization)?;
+
+        // Sessions are only created here, so cleaning up on every save bounds the table.
+        if let Err(e) = self.delete_expired().await {
+            warn!(?e, "Failed to delete expired sessions");
+        }
+
+        loop {
+            let session_key = generate_session_key();
+
+            if self
+                .insert(&session_key, &session_state, ttl)
+                .await
+                .map_err(SaveError::Other)?
+            {
+                return Ok(session_key);
+            }
+        }
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let serialized_state = serde_json::to_value(&session_state)
+            .context("Failed to serialize session state")
+            .map_err(UpdateError::Serialization)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET session_state = $2, expires_at = $3
+            WHERE session_key = $1 AND expires_at > now()
+            "#,
+            session_key.as_ref(),
+            serialized_state,
+            expires_at(ttl),
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to update session state")
+        .map_err(UpdateError::Other)?;
+
+        if result.rows_affected() == 0 {
+            return self.save(session_state, ttl).await.map_err(|e| match e {
+                SaveError::Serialization(e) => UpdateError::Serialization(e),
+                SaveError::Other(e) => UpdateError::Other(e),
+            });
+        }
+
+        Ok(session_key)
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> Result<()> {
+        sqlx::query!(
+            r#"UPDATE sessions SET expires_at = $2 WHERE session_key = $1"#,
+            session_key.as_ref(),
+            expires_at(ttl),
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to update session TTL")?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<()> {
+        sqlx::query!(
+            r#"DELETE FROM sessions WHERE session_key = $1"#,
+            session_key.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to delete session")?;
+
+        Ok(())
+    }
+}
+
+fn generate_session_key() -> SessionKey {
+    let key: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .map(char::from)
+        .take(64)
+        .collect();
+
+    key.try_into()
+        .expect("A 64 characters alphanumeric string is a valid session key")
+}
+
+fn expires_at(ttl: &Duration) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+}
@@ -0,0 +1,122 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::web::{self, Bytes};
+use actix_web_lab::middleware::Next;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// Asks a running [`Application`](crate::startup::Application) to shut down gracefully.
+///
+/// Clones share the same state: triggering any of them stops the application.
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownHandle {
+    token: CancellationToken,
+}
+
+impl ShutdownHandle {
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Completes once shutdown has been triggered.
+    pub async fn triggered(&self) {
+        self.token.cancelled().await
+    }
+}
+
+/// Completes when the process receives SIGTERM or SIGINT.
+pub async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            outcome = tokio::signal::ctrl_c() => outcome,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
+}
+
+/// Counts the requests being served, so that shutdown can wait for them to finish.
+#[derive(Clone, Debug)]
+pub struct InFlightRequests {
+    count: Arc<watch::Sender<usize>>,
+}
+
+impl Default for InFlightRequests {
+    fn default() -> Self {
+        Self {
+            count: Arc::new(watch::channel(0).0),
+        }
+    }
+}
+
+impl InFlightRequests {
+    fn start(&self) -> InFlightGuard {
+        self.count.send_modify(|count| *count += 1);
+        InFlightGuard(self.clone())
+    }
+
+    /// Completes once no request is being served.
+    pub async fn drained(&self) {
+        // The sender lives as long as `self`, so waiting cannot fail.
+        let _ = self.count.subscribe().wait_for(|count| *count == 0).await;
+    }
+}
+
+struct InFlightGuard(InFlightRequests);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.count.send_modify(|count| *count -= 1);
+    }
+}
+
+/// Counts a request as in flight until its response body has been sent in full.
+pub async fn track_in_flight_requests(
+    in_flight_requests: web::Data<InFlightRequests>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<GuardedBody>, actix_web::Error> {
+    let guard = in_flight_requests.start();
+    let response = next.call(req).await?;
+
+    Ok(response.map_body(|_, body| GuardedBody {
+        body: body.boxed(),
+        _guard: guard,
+    }))
+}
+
+/// A response body that keeps its request counted as in flight until it is dropped.
+pub struct GuardedBody {
+    body: BoxBody,
+    _guard: InFlightGuard,
+}
+
+impl MessageBody for GuardedBody {
+    type Error = <BoxBody as MessageBody>::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Pin::new(&mut self.get_mut().body).poll_next(cx)
+    }
+}
@@ -1,39 +1,180 @@
+use std::env;
 use std::net::TcpListener;
+use std::time::Duration;
 
-use actix_web::dev::Server;
+use actix_session::SessionMiddleware;
+use actix_web::cookie::Key;
+use actix_web::dev::{Server, ServerHandle};
 use actix_web::{web, App, HttpServer};
+use actix_web_flash_messages::storage::CookieMessageStore;
+use actix_web_flash_messages::FlashMessagesFramework;
+use actix_web_lab::middleware::from_fn;
 use anyhow::Result;
+use secrecy::ExposeSecret;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::{PgPool, Pool, Postgres};
+use tokio::task::{JoinError, JoinSet};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
 use tracing_actix_web::TracingLogger;
 
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::authentication::{create_user_if_missing, reject_anonymous_users, Credentials};
+use crate::configuration::{BootstrapAdminSettings, DatabaseSettings, Settings};
+use crate::domain::DisposableDomains;
 use crate::email_client::EmailClient;
-use crate::routes::{confirm, health_check, subscribe};
+use crate::email_templates::EmailTemplates;
+use crate::issue_delivery_worker::{run_worker_until_stopped, RetryPolicy};
+use crate::metrics::record_http_metrics;
+use crate::rate_limit::{limit_subscriptions_per_ip, RateLimiter};
+use crate::routes::{
+    admin_dashboard, change_password, change_password_form, confirm, failed_deliveries,
+    health_check, liveness, log_out, login, login_form, metrics, publish_issue, publish_issue_form,
+    publish_newsletter, readiness, requeue_failed_deliveries, subscribe, unsubscribe,
+    unsubscribe_form,
+};
+use crate::session::SessionStoreBackend;
+use crate::shutdown::{track_in_flight_requests, InFlightRequests, ShutdownHandle};
+use crate::subscription_token_cleanup::run_cleanup_until_stopped;
+use crate::unsubscribe::UnsubscribeLinks;
 
 #[derive(Debug)]
 pub struct ApplicationBaseUrl(pub String);
 
-pub fn run(
+#[derive(Debug)]
+pub struct SubscriptionTokenExpiry(pub chrono::Duration);
+
+pub async fn run(
     listener: TcpListener,
     connection: Pool<Postgres>,
     email_client: EmailClient,
-    base_url: String,
-) -> Result<Server, std::io::Error> {
+    email_templates: EmailTemplates,
+    unsubscribe_links: UnsubscribeLinks,
+    in_flight_requests: InFlightRequests,
+    configuration: &Settings,
+) -> Result<Server> {
+    let secret_key = Key::from(
+        configuration
+            .application
+            .session_key
+            .expose_secret()
+            .as_bytes(),
+    );
+    let message_store = CookieMessageStore::builder(secret_key.clone()).build();
+    let message_framework = FlashMessagesFramework::builder(message_store).build();
+    let session_store =
+        SessionStoreBackend::new(configuration.redis_uri.as_ref(), connection.clone()).await?;
+    let rate_limiter = web::Data::new(
+        RateLimiter::new(
+            &configuration.application.rate_limit,
+            configuration.redis_uri.as_ref(),
+        )
+        .await?,
+    );
+
     let connection = web::Data::new(connection);
     let email_client = web::Data::new(email_client);
-    let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let email_templates = web::Data::new(email_templates);
+    let base_url = web::Data::new(ApplicationBaseUrl(
+        configuration.application.base_url.clone(),
+    ));
+    let token_expiry = web::Data::new(SubscriptionTokenExpiry(
+        configuration.subscription_tokens.expiry(),
+    ));
+    let unsubscribe_links = web::Data::new(unsubscribe_links);
+    let disposable_domains = web::Data::new(DisposableDomains::from_file(
+        &configuration.email_validation.disposable_domains_file,
+    )?);
+    let domain_verification = web::Data::new(
+        configuration
+            .email_validation
+            .domain_verification
+            .verification()?,
+    );
+    let popular_domains = web::Data::new(configuration.email_validation.popular_domains());
+    let health_settings = web::Data::new(configuration.health.clone());
+    let serve_metrics = configuration.application.admin_port.is_none();
+    let in_flight_requests = web::Data::new(in_flight_requests);
 
     let server = HttpServer::new(move || {
         App::new()
+            .wrap(message_framework.clone())
+            .wrap(SessionMiddleware::new(
+                session_store.clone(),
+                secret_key.clone(),
+            ))
+            .wrap(from_fn(record_http_metrics))
             .wrap(TracingLogger::default())
+            .wrap(from_fn(track_in_flight_requests))
             .route("/health_check", web::get().to(health_check))
-            .route("/subscriptions", web::post().to(subscribe))
+            .route("/health/live", web::get().to(liveness))
+            .route("/health/ready", web::get().to(readiness))
+            .configure(|cfg| {
+                if serve_metrics {
+                    cfg.route("/metrics", web::get().to(metrics));
+                }
+            })
+            .route("/login", web::get().to(login_form))
+            .route("/login", web::post().to(login))
+            .service(
+                web::resource("/subscriptions")
+                    .wrap(from_fn(limit_subscriptions_per_ip))
+                    .route(web::post().to(subscribe)),
+            )
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route(
+                "/subscriptions/unsubscribe",
+                web::get().to(unsubscribe_form),
+            )
+            .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+            .route("/newsletters", web::post().to(publish_newsletter))
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn(reject_anonymous_users))
+                    .route("/dashboard", web::get().to(admin_dashboard))
+                    .route("/password", web::get().to(change_password_form))
+                    .route("/password", web::post().to(change_password))
+                    .route("/newsletters", web::get().to(publish_issue_form))
+                    .route("/newsletters", web::post().to(publish_issue))
+                    .route("/failed_deliveries", web::get().to(failed_deliveries))
+                    .route(
+                        "/failed_deliveries",
+                        web::post().to(requeue_failed_deliveries),
+                    )
+                    .route("/logout", web::post().to(log_out)),
+            )
             .app_data(connection.clone())
             .app_data(email_client.clone())
+            .app_data(email_templates.clone())
             .app_data(base_url.clone())
+            .app_data(token_expiry.clone())
+            .app_data(unsubscribe_links.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(disposable_domains.clone())
+            .app_data(domain_verification.clone())
+            .app_data(popular_domains.clone())
+            .app_data(health_settings.clone())
+            .app_data(in_flight_requests.clone())
     })
+    // Signals are handled by the caller, through the application's `ShutdownHandle`.
+    .disable_signals()
+    .listen(listener)?
+    .run();
+
+    Ok(server)
+}
+
+/// Serves endpoints meant for operators only, such as `/metrics`, on their own listener.
+pub fn run_admin(listener: TcpListener, connection: Pool<Postgres>) -> Result<Server> {
+    let connection = web::Data::new(connection);
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(TracingLogger::default())
+            .route("/metrics", web::get().to(metrics))
+            .app_data(connection.clone())
+    })
+    .workers(1)
+    .disable_signals()
     .listen(listener)?
     .run();
 
@@ -44,42 +185,219 @@ pub fn get_connection_pool(database: &DatabaseSettings) -> PgPool {
     PgPoolOptions::new().connect_lazy_with(database.with_db())
 }
 
+async fn bootstrap_admin(admin: &BootstrapAdminSettings, pool: &PgPool) -> Result<()> {
+    let credentials = Credentials {
+        username: admin.username.clone(),
+        password: admin.password.clone(),
+    };
+    if create_user_if_missing(credentials, pool).await? {
+        info!(username = %admin.username, "Created the bootstrap admin");
+    }
+    Ok(())
+}
+
 pub struct Application {
     port: u16,
     server: Server,
+    admin_port: Option<u16>,
+    admin_server: Option<Server>,
+    connection_pool: PgPool,
+    email_client: EmailClient,
+    email_templates: EmailTemplates,
+    retry_policy: RetryPolicy,
+    unsubscribe_links: UnsubscribeLinks,
+    token_cleanup_interval: Duration,
+    in_flight_requests: InFlightRequests,
+    shutdown: ShutdownHandle,
+    shutdown_deadline: Duration,
 }
 
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self> {
         let connection_pool = get_connection_pool(&configuration.database);
-        let sender_email = configuration.email_client.sender_email.clone();
-        let timeout = configuration.email_client.timeout();
-        let email_client = EmailClient::new(
-            configuration.email_client.base_url,
-            sender_email,
-            configuration.email_client.authorization_token,
-            timeout,
-        );
+        if let Some(admin) = &configuration.application.bootstrap_admin {
+            bootstrap_admin(admin, &connection_pool).await?;
+        }
+        let email_client = configuration.email_client.client()?;
+        let email_templates =
+            EmailTemplates::from_directory(env::current_dir()?.join("templates"))?;
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
         );
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr().unwrap().port();
+        let unsubscribe_links = UnsubscribeLinks::new(
+            configuration.application.base_url.clone(),
+            configuration.application.unsubscribe_key.clone(),
+        );
+        let in_flight_requests = InFlightRequests::default();
+        let admin_listener = configuration
+            .application
+            .admin_port
+            .map(|port| TcpListener::bind(format!("{}:{}", configuration.application.host, port)))
+            .transpose()?;
+        let admin_port = admin_listener
+            .as_ref()
+            .map(|listener| listener.local_addr().unwrap().port());
+        let admin_server = admin_listener
+            .map(|listener| run_admin(listener, connection_pool.clone()))
+            .transpose()?;
         let server = run(
             listener,
+            connection_pool.clone(),
+            email_client.clone(),
+            email_templates.clone(),
+            unsubscribe_links.clone(),
+            in_flight_requests.clone(),
+            &configuration,
+        )
+        .await?;
+
+        Ok(Self {
+            port,
+            server,
+            admin_port,
+            admin_server,
             connection_pool,
             email_client,
-            configuration.application.base_url,
-        )?;
-
-        Ok(Self { port, server })
+            email_templates,
+            retry_policy: configuration.issue_delivery.retry_policy(),
+            unsubscribe_links,
+            token_cleanup_interval: configuration.subscription_tokens.cleanup_interval(),
+            in_flight_requests,
+            shutdown: ShutdownHandle::default(),
+            shutdown_deadline: configuration.application.shutdown_deadline(),
+        })
     }
+
     pub fn port(&self) -> u16 {
         self.port
     }
 
-    pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+    pub fn admin_port(&self) -> Option<u16> {
+        self.admin_port
+    }
+
+    /// Triggering the returned handle makes `run_until_stopped` shut down gracefully.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Runs until shutdown is triggered or one of the tasks exits, then stops the rest: servers
+    /// stop accepting connections and finish in-flight requests, the background tasks finish
+    /// what they are doing, and the pool is closed. Whatever is still running once the shutdown
+    /// deadline has passed is aborted.
+    pub async fn run_until_stopped(self) -> Result<()> {
+        let server_handles: Vec<_> = std::iter::once(&self.server)
+            .chain(self.admin_server.as_ref())
+            .map(Server::handle)
+            .collect();
+        let mut tasks = JoinSet::new();
+        tasks.spawn(async move {
+            let outcome = serve(self.server, self.admin_server).await;
+            ("API", outcome.map_err(anyhow::Error::from))
+        });
+        tasks.spawn({
+            let cleanup = run_cleanup_until_stopped(
+                self.connection_pool.clone(),
+                self.token_cleanup_interval,
+                self.shutdown.clone(),
+            );
+            async move { ("Subscription token cleanup", cleanup.await) }
+        });
+        tasks.spawn({
+            let worker = run_worker_until_stopped(
+                self.connection_pool.clone(),
+                self.email_client,
+                self.email_templates,
+                self.retry_policy,
+                self.unsubscribe_links,
+                self.shutdown.clone(),
+            );
+            async move { ("Background worker", worker.await) }
+        });
+
+        let mut outcome = tokio::select! {
+            Some(exited) = tasks.join_next() => report_exit(exited),
+            _ = self.shutdown.triggered() => Ok(()),
+        };
+        info!("Shutting down");
+        let deadline = Instant::now() + self.shutdown_deadline;
+        self.shutdown.trigger();
+        drain_servers(&server_handles, &self.in_flight_requests, deadline).await;
+
+        loop {
+            match tokio::time::timeout_at(deadline, tasks.join_next()).await {
+                Ok(Some(exited)) => outcome = outcome.and(report_exit(exited)),
+                Ok(None) => break,
+                Err(_) => {
+                    warn!("Tasks still running at the shutdown deadline were aborted");
+                    tasks.shutdown().await;
+                    break;
+                }
+            }
+        }
+
+        self.connection_pool.close().await;
+        outcome
+    }
+}
+
+/// Runs the API and, if configured, the admin server. Either one exiting stops the other.
+async fn serve(server: Server, admin_server: Option<Server>) -> std::io::Result<()> {
+    let Some(admin_server) = admin_server else {
+        return server.await;
+    };
+    let handles = [server.handle(), admin_server.handle()];
+
+    let outcome = tokio::select! {
+        outcome = server => outcome,
+        outcome = admin_server => outcome,
+    };
+    stop_servers(&handles).await;
+    outcome
+}
+
+/// Stops accepting connections and waits for in-flight requests before stopping the servers.
+///
+/// actix-server's own graceful stop cannot be relied upon for this: a worker may notice the
+/// accept thread going away before it receives the stop command, and drop its connections.
+async fn drain_servers(handles: &[ServerHandle], in_flight: &InFlightRequests, deadline: Instant) {
+    for handle in handles {
+        handle.pause().await;
+    }
+    if tokio::time::timeout_at(deadline, in_flight.drained())
+        .await
+        .is_err()
+    {
+        warn!("Requests still in flight at the shutdown deadline were dropped");
+    }
+    // Only idle keep-alive connections remain, so there is nothing to wait for.
+    for handle in handles {
+        handle.stop(false).await;
+    }
+}
+
+async fn stop_servers(handles: &[ServerHandle]) {
+    for handle in handles {
+        handle.stop(true).await;
+    }
+}
+
+fn report_exit(outcome: Result<(&str, Result<()>), JoinError>) -> Result<()> {
+    match outcome {
+        Ok((task_name, Ok(()))) => {
+            info!("{} has exited", task_name);
+            Ok(())
+        }
+        Ok((task_name, Err(e))) => {
+            error!(?e, "{} failed", task_name);
+            Err(e)
+        }
+        Err(e) => {
+            error!(?e, "A task failed to complete");
+            Err(e.into())
+        }
     }
 }
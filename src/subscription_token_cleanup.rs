@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::shutdown::ShutdownHandle;
+
+pub async fn run_cleanup_until_stopped(
+    pool: PgPool,
+    interval: Duration,
+    shutdown: ShutdownHandle,
+) -> Result<()> {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.triggered() => return Ok(()),
+        }
+        // A failed run is logged by the instrumentation and retried on the next tick.
+        let _ = delete_expired_subscription_tokens(&pool).await;
+    }
+}
+
+pub struct CleanupOutcome {
+    pub deleted_tokens: u64,
+    pub deleted_subscribers: u64,
+}
+
+/// Deletes expired tokens that were never used, then the pending subscribers left without any
+/// token to confirm with. Used tokens are kept, so that following a confirmation link again still
+/// succeeds.
+#[tracing::instrument(skip_all, err)]
+pub async fn delete_expired_subscription_tokens(pool: &PgPool) -> Result<CleanupOutcome> {
+    let mut transaction = pool.begin().await?;
+
+    let deleted_tokens = sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE expires_at <= now() AND consumed_at IS NULL
+        "#
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete expired subscription tokens")?
+    .rows_affected();
+
+    let deleted_subscribers = sqlx::query!(
+        r#"
+        DELETE FROM subscriptions s
+        WHERE s.status = 'pending_confirmation'
+            AND NOT EXISTS (
+                SELECT 1 FROM subscription_tokens t WHERE t.subscriber_id = s.id
+            )
+        "#
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete unconfirmed subscribers")?
+    .rows_affected();
+
+    transaction.commit().await?;
+
+    info!(
+        deleted_tokens,
+        deleted_subscribers, "Cleaned up expired subscription tokens"
+    );
+
+    Ok(CleanupOutcome {
+        deleted_tokens,
+        deleted_subscribers,
+    })
+}
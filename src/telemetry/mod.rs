@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use opentelemetry::propagation::Injector;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tokio::task::JoinHandle;
+use tracing::subscriber::set_global_default;
+use tracing::{Span, Subscriber};
+use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
+use tracing_log::LogTracer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{EnvFilter, Registry};
+
+use crate::configuration::{OtlpProtocol, OtlpSettings};
+
+/// Builds the subscriber writing bunyan logs to `sink`. With `otlp` set, spans are also exported
+/// over OTLP and W3C trace context is propagated from incoming requests.
+///
+/// The exporter runs on the tokio runtime, which must be running when this is called.
+pub fn get_subscriber<Sink>(
+    name: String,
+    env_filter: &str,
+    sink: Sink,
+    otlp: Option<&OtlpSettings>,
+) -> Result<impl Subscriber + Send + Sync>
+where
+    Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let env_filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
+    let otel_layer = otlp
+        .map(|otlp| -> Result<_> {
+            global::set_text_map_propagator(TraceContextPropagator::new());
+            let tracer = otlp_tracer(&name, otlp)?;
+            Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+        })
+        .transpose()?;
+    let formatting_layer = BunyanFormattingLayer::new(name, sink);
+
+    Ok(Registry::default()
+        .with(env_filter)
+        .with(otel_layer)
+        .with(JsonStorageLayer)
+        .with(formatting_layer))
+}
+
+fn otlp_tracer(service_name: &str, otlp: &OtlpSettings) -> Result<trace::Tracer> {
+    let exporter: SpanExporterBuilder = match otlp.protocol {
+        OtlpProtocol::Grpc => opentelemetry_otlp::new_exporter()
+            .tonic()
+            .with_endpoint(&otlp.endpoint)
+            .into(),
+        OtlpProtocol::Http => opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(&otlp.endpoint)
+            .into(),
+    };
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(trace::config().with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            service_name.to_owned(),
+        )])))
+        .install_batch(runtime::Tokio)
+        .context("Failed to install the OTLP span exporter")
+}
+
+pub fn init_subscriber(subscriber: impl Subscriber + Send + Sync) -> anyhow::Result<()> {
+    LogTracer::init()?;
+    set_global_default(subscriber)?;
+
+    Ok(())
+}
+
+/// Exports the spans still buffered. Blocks until the exporter is done.
+pub fn shutdown_tracer_provider() {
+    global::shutdown_tracer_provider();
+}
+
+/// The trace context of the current span, as headers for an outgoing HTTP request.
+/// Empty unless OTLP export is enabled.
+pub fn trace_context_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    headers
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+pub fn spawn_blocking_with_tracing<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let current_span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || current_span.in_scope(f))
+}
+
+#[cfg(test)]
+mod tests;
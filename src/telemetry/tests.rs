@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App, HttpResponse};
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use prost::Message;
+use secrecy::Secret;
+use tracing_actix_web::TracingLogger;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use super::*;
+use crate::email_client::{EmailClient, PostmarkTransport};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+async fn send_email(email_client: web::Data<EmailClient>) -> HttpResponse {
+    email_client
+        .send_email("ursula@example.com", "Hello", "<p>Hi</p>", "Hi")
+        .await
+        .unwrap();
+    HttpResponse::Ok().finish()
+}
+
+// Installs the global tracer provider and propagator: keep it the only test doing so.
+#[tokio::test(flavor = "multi_thread")]
+async fn spans_are_exported_over_otlp_in_the_incoming_trace() {
+    let collector = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/traces"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&collector)
+        .await;
+    let email_server = MockServer::start().await;
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&email_server)
+        .await;
+
+    let otlp = OtlpSettings {
+        endpoint: collector.uri(),
+        protocol: OtlpProtocol::Http,
+    };
+    let subscriber = get_subscriber("test".into(), "info", std::io::sink, Some(&otlp)).unwrap();
+    let guard = tracing::subscriber::set_default(subscriber);
+
+    let email_client = EmailClient::new(
+        "newsletter@example.com".into(),
+        PostmarkTransport::new(
+            email_server.uri(),
+            Secret::new("token".into()),
+            Duration::from_secs(1),
+        ),
+    );
+    let app = init_service(
+        App::new()
+            .wrap(TracingLogger::default())
+            .app_data(web::Data::new(email_client))
+            .route("/", web::get().to(send_email)),
+    )
+    .await;
+    let request = TestRequest::get()
+        .uri("/")
+        .insert_header((
+            "traceparent",
+            format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID),
+        ))
+        .to_request();
+    let response = call_service(&app, request).await;
+    assert!(response.status().is_success());
+
+    // The request span only closes once the response and the app are gone.
+    drop((response, app, guard));
+    tokio::task::spawn_blocking(shutdown_tracer_provider)
+        .await
+        .unwrap();
+
+    // The email provider is called within the same trace, from a span of ours.
+    let email_request = &email_server.received_requests().await.unwrap()[0];
+    let traceparent = email_request.headers.get(&"traceparent".into()).unwrap();
+    let traceparent = traceparent.last().as_str();
+    assert!(traceparent.starts_with(&format!("00-{}-", TRACE_ID)));
+    assert!(!traceparent.contains(PARENT_SPAN_ID));
+
+    let exported_spans: Vec<_> = collector
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .flat_map(|request| {
+            ExportTraceServiceRequest::decode(request.body.as_slice())
+                .unwrap()
+                .resource_spans
+        })
+        .flat_map(|resource_spans| resource_spans.scope_spans)
+        .flat_map(|scope_spans| scope_spans.spans)
+        .collect();
+    let root_span = exported_spans
+        .iter()
+        .find(|span| hex(&span.parent_span_id) == PARENT_SPAN_ID)
+        .expect("No span was exported as a child of the incoming trace");
+    assert_eq!(hex(&root_span.trace_id), TRACE_ID);
+    assert!(exported_spans
+        .iter()
+        .all(|span| hex(&span.trace_id) == TRACE_ID));
+}
+
+#[test]
+fn no_trace_context_is_sent_without_an_exporter() {
+    let subscriber = get_subscriber("test".into(), "info", std::io::sink, None).unwrap();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let headers = tracing::info_span!("outgoing").in_scope(trace_context_headers);
+
+    assert!(headers.is_empty());
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,73 @@
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Builds and verifies the per-subscriber links used to leave the newsletter.
+///
+/// A token is the subscriber id followed by an HMAC of that id, so links can be checked
+/// without storing anything in the database.
+#[derive(Clone)]
+pub struct UnsubscribeLinks {
+    base_url: String,
+    key: Secret<String>,
+}
+
+impl UnsubscribeLinks {
+    pub fn new(base_url: String, key: Secret<String>) -> Self {
+        Self { base_url, key }
+    }
+
+    pub fn link(&self, subscriber_id: Uuid) -> String {
+        format!(
+            "{}/subscriptions/unsubscribe?token={}",
+            self.base_url,
+            self.token(subscriber_id)
+        )
+    }
+
+    pub fn token(&self, subscriber_id: Uuid) -> String {
+        let signature = self.mac(subscriber_id).finalize().into_bytes();
+
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(subscriber_id.as_bytes()),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Uuid> {
+        let (subscriber_id, signature) = token
+            .split_once('.')
+            .context("The token is not in the expected format")?;
+        let subscriber_id = URL_SAFE_NO_PAD
+            .decode(subscriber_id)
+            .context("The subscriber id is not valid base64")?;
+        let subscriber_id =
+            Uuid::from_slice(&subscriber_id).context("The subscriber id is not a valid UUID")?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .context("The signature is not valid base64")?;
+
+        if self.mac(subscriber_id).verify_slice(&signature).is_err() {
+            bail!("The token signature does not match");
+        }
+
+        Ok(subscriber_id)
+    }
+
+    fn mac(&self, subscriber_id: Uuid) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(self.key.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(subscriber_id.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests;
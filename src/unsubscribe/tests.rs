@@ -0,0 +1,58 @@
+use claims::{assert_err, assert_ok_eq};
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::unsubscribe::UnsubscribeLinks;
+
+fn links(key: &str) -> UnsubscribeLinks {
+    UnsubscribeLinks::new("http://localhost".into(), Secret::new(key.into()))
+}
+
+#[test]
+fn a_signed_token_is_accepted() {
+    let links = links("secret");
+    let subscriber_id = Uuid::new_v4();
+
+    assert_ok_eq!(links.verify(&links.token(subscriber_id)), subscriber_id);
+}
+
+#[test]
+fn a_token_signed_with_another_key_is_rejected() {
+    let token = links("another-secret").token(Uuid::new_v4());
+
+    assert_err!(links("secret").verify(&token));
+}
+
+#[test]
+fn a_token_for_another_subscriber_is_rejected() {
+    let links = links("secret");
+    let first_token = links.token(Uuid::new_v4());
+    let second_token = links.token(Uuid::new_v4());
+    let (_, signature) = first_token.split_once('.').unwrap();
+    let (subscriber_id, _) = second_token.split_once('.').unwrap();
+
+    assert_err!(links.verify(&format!("{}.{}", subscriber_id, signature)));
+}
+
+#[test]
+fn malformed_tokens_are_rejected() {
+    let links = links("secret");
+
+    for token in ["", "no-separator", "!!!.!!!", "AAAA.AAAA"] {
+        assert_err!(links.verify(token), "{} should be rejected", token);
+    }
+}
+
+#[test]
+fn the_link_points_to_the_unsubscribe_endpoint() {
+    let links = links("secret");
+    let subscriber_id = Uuid::new_v4();
+
+    assert_eq!(
+        links.link(subscriber_id),
+        format!(
+            "http://localhost/subscriptions/unsubscribe?token={}",
+            links.token(subscriber_id)
+        )
+    );
+}
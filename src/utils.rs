@@ -0,0 +1,173 @@
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::http::header::{Accept, ContentType, Header, LOCATION};
+use actix_web::http::StatusCode;
+use actix_web::{FromRequest, HttpRequest, HttpResponse};
+use serde::Serialize;
+
+use crate::domain::FieldError;
+
+pub fn see_other(location: &str) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((LOCATION, location))
+        .finish()
+}
+
+/// Formats an error followed by every error in its `source()` chain.
+pub fn error_chain_fmt(
+    e: &impl std::error::Error,
+    f: &mut std::fmt::Formatter<'_>,
+) -> std::fmt::Result {
+    writeln!(f, "{}\n", e)?;
+    let mut current = e.source();
+    while let Some(cause) = current {
+        writeln!(f, "Caused by:\n\t{}", cause)?;
+        current = cause.source();
+    }
+    Ok(())
+}
+
+/// An RFC 7807 problem details body.
+#[derive(Serialize, Debug)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: &'static str,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode, detail: Option<String>) -> Self {
+        Self {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Unknown error"),
+            status: status.as_u16(),
+            detail,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn with_errors(mut self, errors: Vec<FieldError>) -> Self {
+        self.errors = errors;
+        self
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        HttpResponse::build(StatusCode::from_u16(self.status).unwrap())
+            .content_type("application/problem+json")
+            .json(self)
+    }
+
+    /// Renders the problem in `format`, for clients that do not expect JSON.
+    pub fn into_response_as(self, format: ResponseFormat) -> HttpResponse {
+        let (content_type, body) = match format {
+            ResponseFormat::Json => return self.into_response(),
+            ResponseFormat::PlainText => (ContentType::plaintext(), self.to_plain_text()),
+            ResponseFormat::Html => (ContentType::html(), self.to_html()),
+        };
+
+        HttpResponse::build(StatusCode::from_u16(self.status).unwrap())
+            .content_type(content_type)
+            .body(body)
+    }
+
+    fn to_plain_text(&self) -> String {
+        let mut text = format!("{}\n", self.title);
+        if let Some(detail) = &self.detail {
+            writeln!(text, "{}", detail).unwrap();
+        }
+        for error in &self.errors {
+            write!(text, "{}: {}", error.field, error.message).unwrap();
+            if let Some(suggestion) = &error.suggestion {
+                write!(text, " (did you mean {}?)", suggestion).unwrap();
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn to_html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head><title>{0}</title></head>\n<body>\n<h1>{0}</h1>\n",
+            self.title
+        );
+        if let Some(detail) = &self.detail {
+            writeln!(html, "<p>{}</p>", htmlescape::encode_minimal(detail)).unwrap();
+        }
+        if !self.errors.is_empty() {
+            html.push_str("<ul>\n");
+            for error in &self.errors {
+                write!(
+                    html,
+                    "<li>{}: {}",
+                    error.field,
+                    htmlescape::encode_minimal(&error.message)
+                )
+                .unwrap();
+                if let Some(suggestion) = &error.suggestion {
+                    write!(
+                        html,
+                        " (did you mean {}?)",
+                        htmlescape::encode_minimal(suggestion)
+                    )
+                    .unwrap();
+                }
+                html.push_str("</li>\n");
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+/// How to render a response body, negotiated from the request's `Accept` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Html,
+    PlainText,
+}
+
+impl FromRequest for ResponseFormat {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(Self::negotiate(req)))
+    }
+}
+
+impl ResponseFormat {
+    /// Picks the client's most preferred format that we can produce, defaulting to JSON when the
+    /// header is missing, invalid or only lists formats we cannot produce.
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        let Ok(accept) = Accept::parse(req) else {
+            return ResponseFormat::Json;
+        };
+
+        accept
+            .ranked()
+            .iter()
+            .find_map(|mime| {
+                let suffix = mime.suffix().map(|suffix| suffix.as_str());
+                match (mime.type_().as_str(), mime.subtype().as_str(), suffix) {
+                    ("application", "json", _) | ("application", _, Some("json")) => {
+                        Some(ResponseFormat::Json)
+                    }
+                    ("text", "html", _) => Some(ResponseFormat::Html),
+                    ("text", "plain" | "*", _) => Some(ResponseFormat::PlainText),
+                    ("*", "*", _) | ("application", "*", _) => Some(ResponseFormat::Json),
+                    _ => None,
+                }
+            })
+            .unwrap_or(ResponseFormat::Json)
+    }
+}
@@ -0,0 +1,26 @@
+use anyhow::Result;
+
+use crate::common::{assert_is_redirect_to, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_access_the_admin_dashboard() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app.get("/admin/dashboard").await?;
+
+    assert_is_redirect_to(&response, "/login");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_dashboard_greets_the_logged_in_user() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.login_test_user().await?;
+
+    let html_page = test_app.get_admin_dashboard_html().await?;
+
+    assert!(html_page.contains(&format!("Welcome {}", test_app.test_user.username)));
+
+    Ok(())
+}
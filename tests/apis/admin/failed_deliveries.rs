@@ -0,0 +1,79 @@
+use anyhow::Result;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::common::{assert_is_redirect_to, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_failed_deliveries() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app.get("/admin/failed_deliveries").await?;
+
+    assert_is_redirect_to(&response, "/login");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_requeue_failed_deliveries() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app
+        .post_requeue_failed_deliveries(&serde_json::json!({}))
+        .await?;
+
+    assert_is_redirect_to(&response, "/login");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_listed_and_can_be_requeued() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    test_app.login_test_user().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(400))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    test_app
+        .post_newsletters(serde_json::json!({
+            "title": "Failing issue",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .await?;
+    test_app.dispatch_all_pending_emails().await?;
+
+    let html_page = test_app.get_failed_deliveries_html().await?;
+    assert!(html_page.contains("Failing issue"));
+    assert!(html_page.contains("ursula_le_guin@gmail.com"));
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_requeue_failed_deliveries(&serde_json::json!({}))
+        .await?;
+    assert_is_redirect_to(&response, "/admin/failed_deliveries");
+
+    let html_page = test_app.get_failed_deliveries_html().await?;
+    assert!(html_page.contains("<p><i>Re-queued 1 failed deliveries.</i></p>"));
+    assert!(!html_page.contains("Failing issue"));
+
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+
+use crate::common::{assert_is_redirect_to, TestApp};
+
+#[tokio::test]
+async fn logout_clears_session_state() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app.login_test_user().await?;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let response = test_app.post_logout().await?;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = test_app.get_login_html().await?;
+    assert!(html_page.contains("<p><i>You have successfully logged out.</i></p>"));
+
+    let saved = sqlx::query!("SELECT count(*) AS \"count!\" FROM sessions")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert_eq!(saved.count, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn logout_redirects_anonymous_users_to_the_login_form() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app.post_logout().await?;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = test_app.get_login_html().await?;
+    assert!(!html_page.contains("You have successfully logged out."));
+
+    Ok(())
+}
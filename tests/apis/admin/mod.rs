@@ -0,0 +1,5 @@
+mod dashboard;
+mod failed_deliveries;
+mod logout;
+mod newsletters;
+mod password;
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::common::{assert_is_redirect_to, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_newsletter_form() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app.get("/admin/newsletters").await?;
+
+    assert_is_redirect_to(&response, "/login");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_publish_a_newsletter() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app.post_publish_issue(&issue_form_body()).await?;
+
+    assert_is_redirect_to(&response, "/login");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_unconfirmed_subscriber().await?;
+    test_app.login_test_user().await?;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.post_publish_issue(&issue_form_body()).await?;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = test_app.get_publish_issue_html().await?;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - emails will go out shortly.</i></p>"
+    ));
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_are_delivered_to_confirmed_subscribers() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    test_app.login_test_user().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.post_publish_issue(&issue_form_body()).await?;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = test_app.get_publish_issue_html().await?;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - emails will go out shortly.</i></p>"
+    ));
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn an_empty_text_content_is_generated_from_the_html() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    test_app.login_test_user().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let mut body = issue_form_body();
+    body["text_content"] = "".into();
+    let response = test_app.post_publish_issue(&body).await?;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    test_app.dispatch_all_pending_emails().await?;
+
+    let requests = test_app
+        .email_server
+        .received_requests()
+        .await
+        .context("No requests")?;
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)?;
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("Newsletter body as HTML"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletter_creation_is_idempotent() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    test_app.login_test_user().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let body = issue_form_body();
+
+    let response = test_app.post_publish_issue(&body).await?;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = test_app.get_publish_issue_html().await?;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - emails will go out shortly.</i></p>"
+    ));
+
+    let response = test_app.post_publish_issue(&body).await?;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = test_app.get_publish_issue_html().await?;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - emails will go out shortly.</i></p>"
+    ));
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn concurrent_form_submission_is_handled_gracefully() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    test_app.login_test_user().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let body = issue_form_body();
+    let response1 = test_app.post_publish_issue(&body);
+    let response2 = test_app.post_publish_issue(&body);
+    let (response1, response2) = tokio::join!(response1, response2);
+    let (response1, response2) = (response1?, response2?);
+
+    assert_eq!(response1.status(), response2.status());
+    assert_eq!(response1.text().await?, response2.text().await?);
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_idempotency_keys_are_rejected() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.login_test_user().await?;
+
+    let mut body = issue_form_body();
+    body["idempotency_key"] = serde_json::Value::String(String::new());
+
+    let response = test_app.post_publish_issue(&body).await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    Ok(())
+}
+
+fn issue_form_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    })
+}
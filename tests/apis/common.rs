@@ -1,29 +1,55 @@
 use std::sync::OnceLock;
 
 use anyhow::{bail, Context, Result};
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{Connection, Error, Executor, PgConnection, PgPool, Pool, Postgres};
-use wiremock::MockServer;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
-use zero2prod::configuration::{DatabaseSettings, Settings};
+use zero2prod::authentication::compute_password_hash;
+use zero2prod::configuration::{DatabaseSettings, DomainVerifierKind, Settings};
+use zero2prod::email_client::EmailClient;
+use zero2prod::email_templates::EmailTemplates;
+use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome, RetryPolicy};
+use zero2prod::shutdown::ShutdownHandle;
 use zero2prod::startup::{get_connection_pool, Application};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::unsubscribe::UnsubscribeLinks;
 
 static TRACING: OnceLock<()> = OnceLock::new();
 
 pub struct TestApp {
     pub address: String,
     pub port: u16,
+    pub admin_address: Option<String>,
     pub db_pool: PgPool,
     pub email_server: MockServer,
+    pub test_user: TestUser,
+    pub api_client: reqwest::Client,
+    pub email_client: EmailClient,
+    pub email_templates: EmailTemplates,
+    pub retry_policy: RetryPolicy,
+    pub unsubscribe_links: UnsubscribeLinks,
+    pub shutdown_handle: ShutdownHandle,
+    pub application: JoinHandle<Result<()>>,
 }
 
 impl TestApp {
     pub async fn new() -> Result<TestApp> {
+        Self::with_configuration(|_| {}).await
+    }
+
+    /// Spawns an app with test overrides applied on top of the base configuration.
+    pub async fn with_configuration(customise: impl FnOnce(&mut Settings)) -> Result<TestApp> {
         init_tracing();
 
         let mut configuration = Settings::get_configuration()?;
-        configuration.database.database_name = uuid::Uuid::new_v4().to_string();
+        configuration.database.database_name = Uuid::new_v4().to_string();
         configuration.application.port = 0;
+        configuration.email_validation.domain_verification.kind = DomainVerifierKind::InMemory;
+        customise(&mut configuration);
 
         configure_database(&mut configuration.database).await?;
 
@@ -33,17 +59,49 @@ impl TestApp {
         let application = Application::build(configuration.clone()).await?;
         let port = application.port();
         let address = format!("http://127.0.0.1:{}", port);
+        let admin_address = application
+            .admin_port()
+            .map(|port| format!("http://127.0.0.1:{}", port));
+
+        let shutdown_handle = application.shutdown_handle();
+        let application = tokio::spawn(application.run_until_stopped());
+
+        let db_pool = get_connection_pool(&configuration.database);
 
-        drop(tokio::spawn(application.run_until_stopped()));
+        let test_user = TestUser::generate();
+        test_user.store(&db_pool).await?;
+
+        let api_client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .cookie_store(true)
+            .build()?;
 
         Ok(TestApp {
             address,
             port,
-            db_pool: get_connection_pool(&configuration.database),
+            admin_address,
+            db_pool,
             email_server,
+            test_user,
+            api_client,
+            email_client: configuration.email_client.client()?,
+            email_templates: EmailTemplates::from_directory("templates")?,
+            retry_policy: configuration.issue_delivery.retry_policy(),
+            unsubscribe_links: UnsubscribeLinks::new(
+                configuration.application.base_url,
+                configuration.application.unsubscribe_key,
+            ),
+            shutdown_handle,
+            application,
         })
     }
 
+    /// Triggers a graceful shutdown and waits for the application to stop.
+    pub async fn stop(&mut self) -> Result<()> {
+        self.shutdown_handle.trigger();
+        (&mut self.application).await?
+    }
+
     pub async fn post_subscriptions(&self, body: String) -> Result<reqwest::Response> {
         let response = reqwest::Client::new()
             .post(format!("{}/subscriptions", self.address))
@@ -54,6 +112,271 @@ impl TestApp {
 
         Ok(response)
     }
+
+    pub async fn post_subscriptions_json(
+        &self,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response> {
+        let response = reqwest::Client::new()
+            .post(format!("{}/subscriptions", self.address))
+            .json(body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn dispatch_all_pending_emails(&self) -> Result<()> {
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.email_templates,
+                &self.retry_policy,
+                &self.unsubscribe_links,
+            )
+            .await?
+            {
+                // The background worker may still be holding a task: wait for it to be done.
+                let pending = sqlx::query!(
+                    r#"
+                    SELECT count(*) AS "count!"
+                    FROM issue_delivery_queue
+                    WHERE next_attempt_at <= now()
+                    "#
+                )
+                .fetch_one(&self.db_pool)
+                .await?
+                .count;
+                if pending == 0 {
+                    return Ok(());
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        }
+    }
+
+    pub async fn post_newsletters(&self, body: serde_json::Value) -> Result<reqwest::Response> {
+        let response = reqwest::Client::new()
+            .post(format!("{}/newsletters", self.address))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .json(&body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn post_newsletters_with_idempotency_key(
+        &self,
+        body: serde_json::Value,
+        idempotency_key: &str,
+    ) -> Result<reqwest::Response> {
+        let response = reqwest::Client::new()
+            .post(format!("{}/newsletters", self.address))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .header("Idempotency-Key", idempotency_key)
+            .json(&body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn post_login<Body>(&self, body: &Body) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .api_client
+            .post(format!("{}/login", self.address))
+            .form(body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn get_login_html(&self) -> Result<String> {
+        self.get_html("/login").await
+    }
+
+    pub async fn post_logout(&self) -> Result<reqwest::Response> {
+        let response = self
+            .api_client
+            .post(format!("{}/admin/logout", self.address))
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn get_admin_dashboard_html(&self) -> Result<String> {
+        self.get_html("/admin/dashboard").await
+    }
+
+    pub async fn get_change_password_html(&self) -> Result<String> {
+        self.get_html("/admin/password").await
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .api_client
+            .post(format!("{}/admin/password", self.address))
+            .form(body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn get_publish_issue_html(&self) -> Result<String> {
+        self.get_html("/admin/newsletters").await
+    }
+
+    pub async fn post_publish_issue<Body>(&self, body: &Body) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .api_client
+            .post(format!("{}/admin/newsletters", self.address))
+            .form(body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn get_failed_deliveries_html(&self) -> Result<String> {
+        self.get_html("/admin/failed_deliveries").await
+    }
+
+    pub async fn post_requeue_failed_deliveries<Body>(
+        &self,
+        body: &Body,
+    ) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .api_client
+            .post(format!("{}/admin/failed_deliveries", self.address))
+            .form(body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn get(&self, route: &str) -> Result<reqwest::Response> {
+        let response = self
+            .api_client
+            .get(format!("{}{}", self.address, route))
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    pub async fn post_unsubscribe(&self, token: &str) -> Result<reqwest::Response> {
+        let response = self
+            .api_client
+            .post(format!("{}/subscriptions/unsubscribe", self.address))
+            .query(&[("token", token)])
+            .form(&[("List-Unsubscribe", "One-Click")])
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    async fn get_html(&self, route: &str) -> Result<String> {
+        Ok(self.get(route).await?.text().await?)
+    }
+
+    pub async fn create_unconfirmed_subscriber(&self) -> Result<ConfirmationLinks> {
+        let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+        let _mock_guard = Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .named("Create unconfirmed subscriber")
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+
+        self.post_subscriptions(body.to_string())
+            .await?
+            .error_for_status()?;
+
+        let requests = self
+            .email_server
+            .received_requests()
+            .await
+            .context("No requests")?;
+        let email_request = requests.last().context("Empty requests")?;
+
+        ConfirmationLinks::try_from(email_request, self.port)
+    }
+
+    pub async fn create_confirmed_subscriber(&self) -> Result<()> {
+        let confirmation_links = self.create_unconfirmed_subscriber().await?;
+
+        reqwest::get(confirmation_links.html)
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    pub async fn login_test_user(&self) -> Result<reqwest::Response> {
+        self.post_login(&serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password,
+        }))
+        .await
+    }
+}
+
+pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers()["Location"], location);
+}
+
+pub struct TestUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub password: String,
+}
+
+impl TestUser {
+    pub fn generate() -> Self {
+        Self {
+            user_id: Uuid::new_v4(),
+            username: Uuid::new_v4().to_string(),
+            password: Uuid::new_v4().to_string(),
+        }
+    }
+
+    async fn store(&self, pool: &PgPool) -> Result<()> {
+        let password_hash = compute_password_hash(Secret::new(self.password.clone()))?;
+
+        sqlx::query!(
+            r#"INSERT INTO users (user_id, username, password_hash) VALUES ($1, $2, $3)"#,
+            self.user_id,
+            self.username,
+            password_hash.expose_secret(),
+        )
+        .execute(pool)
+        .await
+        .context("Failed to store test user")?;
+
+        Ok(())
+    }
 }
 
 fn init_tracing() {
@@ -62,10 +385,13 @@ fn init_tracing() {
         let default_filter_level = "info";
 
         if std::env::var("TEST_LOG").is_ok() {
-            let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+            let subscriber =
+                get_subscriber(subscriber_name, default_filter_level, std::io::stdout, None)
+                    .unwrap();
             init_subscriber(subscriber).unwrap();
         } else {
-            let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+            let subscriber =
+                get_subscriber(subscriber_name, default_filter_level, std::io::sink, None).unwrap();
             init_subscriber(subscriber).unwrap();
         };
     });
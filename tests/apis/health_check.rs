@@ -1,4 +1,8 @@
+use std::time::{Duration, Instant};
+
 use anyhow::Result;
+use wiremock::matchers::{header_exists, method, path};
+use wiremock::{Mock, ResponseTemplate};
 
 use crate::common::TestApp;
 
@@ -18,3 +22,129 @@ async fn health_check_works() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn liveness_does_not_depend_on_anything() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    sqlx::query("DROP TABLE _sqlx_migrations")
+        .execute(&test_app.db_pool)
+        .await?;
+
+    let response = test_app.get("/health/live").await?;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn readiness_reports_every_component_as_up() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app.get("/health/ready").await?;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "status": "ready",
+            "components": {
+                "database": {"status": "up"},
+                "migrations": {"status": "up"},
+            },
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn readiness_is_degraded_when_a_migration_is_missing() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    sqlx::query(
+        "DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)",
+    )
+    .execute(&test_app.db_pool)
+    .await?;
+
+    let response = test_app.get("/health/ready").await?;
+
+    assert_eq!(response.status().as_u16(), 503);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["components"]["database"]["status"], "up");
+    assert_eq!(body["components"]["migrations"]["status"], "down");
+    assert!(body["components"]["migrations"]["detail"]
+        .as_str()
+        .unwrap()
+        .contains("has not been applied"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn readiness_pings_the_email_provider_when_enabled() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| c.health.check_email_provider = true).await?;
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .and(header_exists("X-Postmark-Server-Token"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.get("/health/ready").await?;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["components"]["email"]["status"], "up");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn readiness_is_degraded_when_the_email_provider_rejects_the_ping() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| c.health.check_email_provider = true).await?;
+    Mock::given(path("/server"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.get("/health/ready").await?;
+
+    assert_eq!(response.status().as_u16(), 503);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["components"]["email"]["status"], "down");
+    assert_eq!(body["components"]["database"]["status"], "up");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn readiness_checks_are_bounded_in_time() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| {
+        c.health.check_email_provider = true;
+        c.health.timeout_milliseconds = 200;
+    })
+    .await?;
+    Mock::given(path("/server"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&test_app.email_server)
+        .await;
+
+    let start = Instant::now();
+    let response = test_app.get("/health/ready").await?;
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(response.status().as_u16(), 503);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["components"]["email"]["status"], "down");
+    assert_eq!(
+        body["components"]["email"]["detail"],
+        "Timed out after 200ms"
+    );
+
+    Ok(())
+}
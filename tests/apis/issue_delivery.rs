@@ -0,0 +1,212 @@
+use anyhow::Result;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::common::TestApp;
+
+#[tokio::test]
+async fn transient_failures_are_retried_later() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_issue(&test_app).await?;
+
+    let task = sqlx::query!(
+        r#"SELECT n_attempts, next_attempt_at > now() AS "is_delayed!" FROM issue_delivery_queue"#
+    )
+    .fetch_one(&test_app.db_pool)
+    .await?;
+    assert_eq!(task.n_attempts, 1);
+    assert!(task.is_delayed);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    make_pending_tasks_due(&test_app).await?;
+    test_app.dispatch_all_pending_emails().await?;
+
+    assert_eq!(count(&test_app, "issue_delivery_queue").await?, 0);
+    assert_eq!(count(&test_app, "failed_deliveries").await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn permanent_failures_are_moved_to_failed_deliveries() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_issue(&test_app).await?;
+
+    assert_eq!(count(&test_app, "issue_delivery_queue").await?, 0);
+
+    let failed = sqlx::query!(
+        r#"
+        SELECT s.email, f.n_attempts, f.last_error
+        FROM failed_deliveries f
+        JOIN subscriptions s ON s.id = f.subscriber_id
+        "#
+    )
+    .fetch_one(&test_app.db_pool)
+    .await?;
+    assert_eq!(failed.email, "ursula_le_guin@gmail.com");
+    assert_eq!(failed.n_attempts, 1);
+    assert!(failed.last_error.contains("422"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn deliveries_are_given_up_after_the_maximum_number_of_attempts() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let max_attempts = test_app.retry_policy.max_attempts;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(max_attempts as u64)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_issue(&test_app).await?;
+    for _ in 1..max_attempts {
+        make_pending_tasks_due(&test_app).await?;
+        test_app.dispatch_all_pending_emails().await?;
+    }
+
+    assert_eq!(count(&test_app, "issue_delivery_queue").await?, 0);
+
+    let failed = sqlx::query!("SELECT n_attempts FROM failed_deliveries")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert_eq!(failed.n_attempts, max_attempts);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tasks_that_cannot_be_prepared_are_given_up_after_the_maximum_number_of_attempts(
+) -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let max_attempts = test_app.retry_policy.max_attempts;
+
+    // Nobody has subscribed yet, so publishing does not queue any delivery.
+    publish_issue(&test_app).await?;
+    test_app.create_confirmed_subscriber().await?;
+    // Loading the issue fails on every attempt.
+    sqlx::query("ALTER TABLE newsletter_issues RENAME COLUMN title TO renamed_title")
+        .execute(&test_app.db_pool)
+        .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_id)
+        SELECT newsletter_issue_id, id FROM newsletter_issues, subscriptions
+        "#
+    )
+    .execute(&test_app.db_pool)
+    .await?;
+
+    for _ in 0..max_attempts {
+        make_pending_tasks_due(&test_app).await?;
+        test_app.dispatch_all_pending_emails().await?;
+    }
+
+    assert_eq!(count(&test_app, "issue_delivery_queue").await?, 0);
+
+    let failed = sqlx::query!("SELECT n_attempts, last_error FROM failed_deliveries")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert_eq!(failed.n_attempts, max_attempts);
+    assert!(failed
+        .last_error
+        .contains(r#"column "title" does not exist"#));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn queued_deliveries_follow_the_subscriber_when_their_address_changes() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_issue(&test_app).await?;
+    sqlx::query!("UPDATE subscriptions SET email = 'Ursula_Le_Guin@gmail.com'")
+        .execute(&test_app.db_pool)
+        .await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    make_pending_tasks_due(&test_app).await?;
+    test_app.dispatch_all_pending_emails().await?;
+
+    let requests = test_app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)?;
+    assert_eq!(body["To"], "Ursula_Le_Guin@gmail.com");
+    assert_eq!(count(&test_app, "issue_delivery_queue").await?, 0);
+
+    Ok(())
+}
+
+async fn publish_issue(test_app: &TestApp) -> Result<()> {
+    let response = test_app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .await?;
+    assert_eq!(response.status().as_u16(), 202);
+
+    test_app.dispatch_all_pending_emails().await
+}
+
+async fn make_pending_tasks_due(test_app: &TestApp) -> Result<()> {
+    sqlx::query!("UPDATE issue_delivery_queue SET next_attempt_at = now()")
+        .execute(&test_app.db_pool)
+        .await?;
+
+    Ok(())
+}
+
+async fn count(test_app: &TestApp, table: &str) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar(&format!("SELECT count(*) FROM {}", table))
+        .fetch_one(&test_app.db_pool)
+        .await?;
+
+    Ok(count)
+}
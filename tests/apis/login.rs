@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+
+use secrecy::Secret;
+use zero2prod::authentication::{create_user_if_missing, Credentials};
+use zero2prod::configuration::BootstrapAdminSettings;
+
+use crate::common::{assert_is_redirect_to, TestApp};
+
+#[tokio::test]
+async fn an_error_flash_message_is_set_on_failure() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let login_body = serde_json::json!({
+        "username": "random-username",
+        "password": "random-password",
+    });
+    let response = test_app.post_login(&login_body).await?;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = test_app.get_login_html().await?;
+    assert!(html_page.contains("<p><i>Authentication failed</i></p>"));
+
+    let html_page = test_app.get_login_html().await?;
+    assert!(!html_page.contains("Authentication failed"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn redirect_to_admin_dashboard_after_login_success() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app.login_test_user().await?;
+
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sessions_are_persisted_in_the_database() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    test_app.login_test_user().await?;
+
+    let saved = sqlx::query!("SELECT session_state FROM sessions")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    let user_id = saved.session_state["user_id"]
+        .as_str()
+        .context("No user id in the session state")?;
+
+    assert_eq!(user_id, serde_json::to_string(&test_app.test_user.user_id)?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_sessions_are_deleted_when_a_session_is_saved() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO sessions (session_key, session_state, expires_at)
+        VALUES ('expired', '{}', now() - interval '1 second')
+        "#
+    )
+    .execute(&test_app.db_pool)
+    .await?;
+
+    test_app.login_test_user().await?;
+
+    let keys: Vec<String> = sqlx::query_scalar!("SELECT session_key FROM sessions")
+        .fetch_all(&test_app.db_pool)
+        .await?;
+    assert_eq!(keys.len(), 1);
+    assert_ne!(keys[0], "expired");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_session_is_rotated_on_login() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let first_session = session_cookie(&test_app.login_test_user().await?)?;
+    let second_session = session_cookie(&test_app.login_test_user().await?)?;
+
+    assert_ne!(first_session, second_session);
+
+    Ok(())
+}
+
+fn session_cookie(response: &reqwest::Response) -> Result<String> {
+    let cookie = response
+        .cookies()
+        .find(|c| c.name() == "id")
+        .context("No session cookie set")?;
+
+    Ok(cookie.value().to_string())
+}
+
+#[tokio::test]
+async fn no_user_is_created_by_the_migrations() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let usernames: Vec<String> = sqlx::query_scalar!("SELECT username FROM users")
+        .fetch_all(&test_app.db_pool)
+        .await?;
+
+    assert_eq!(usernames.len(), 1);
+    assert_eq!(usernames[0], test_app.test_user.username);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_bootstrap_admin_can_log_in() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| {
+        c.application.bootstrap_admin = Some(BootstrapAdminSettings {
+            username: "bootstrap-admin".into(),
+            password: Secret::new("a-long-bootstrap-password".into()),
+        });
+    })
+    .await?;
+
+    let response = test_app
+        .post_login(&serde_json::json!({
+            "username": "bootstrap-admin",
+            "password": "a-long-bootstrap-password",
+        }))
+        .await?;
+
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_bootstrap_admin_does_not_overwrite_an_existing_user() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let created = create_user_if_missing(
+        Credentials {
+            username: test_app.test_user.username.clone(),
+            password: Secret::new("another-password".into()),
+        },
+        &test_app.db_pool,
+    )
+    .await?;
+
+    assert!(!created);
+    let response = test_app.login_test_user().await?;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    Ok(())
+}
@@ -1,3 +1,9 @@
+mod admin;
 mod common;
 mod health_check;
+mod issue_delivery;
+mod login;
+mod metrics;
+mod newsletters;
+mod shutdown;
 mod subscriptions;
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::common::TestApp;
+
+async fn get_metrics(address: &str) -> Result<reqwest::Response> {
+    Ok(reqwest::get(format!("{}/metrics", address)).await?)
+}
+
+#[tokio::test]
+async fn metrics_are_exposed_in_the_prometheus_text_format() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    test_app.get("/health_check").await?;
+    let response = get_metrics(&test_app.address).await?;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["Content-Type"]
+        .to_str()?
+        .starts_with("text/plain; version=0.0.4"));
+    let body = response.text().await?;
+    assert!(
+        body.contains(r#"http_requests_total{method="GET",route="/health_check",status="200"}"#)
+    );
+    assert!(body.contains(
+        r#"http_request_duration_seconds_bucket{method="GET",route="/health_check",status="200","#
+    ));
+    assert!(body.contains(r#"db_pool_connections{state="max"} 10"#));
+    assert!(body.contains(r#"db_pool_connections{state="in_use"}"#));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_are_labelled_with_their_route_pattern() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    test_app
+        .get("/subscriptions/confirm?subscription_token=abc")
+        .await?;
+    test_app.get("/no/such/page").await?;
+    let body = get_metrics(&test_app.address).await?.text().await?;
+
+    assert!(body.contains(r#"route="/subscriptions/confirm",status="401""#));
+    assert!(body.contains(r#"route="unmatched",status="404""#));
+    assert!(!body.contains("/no/such/page"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn email_sends_are_counted_by_provider_response_code() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&test_app.email_server)
+        .await;
+
+    test_app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await?;
+    let body = get_metrics(&test_app.address).await?.text().await?;
+
+    assert!(body.contains(r#"email_send_failures_total{code="503",provider="postmark"}"#));
+    assert!(body.contains(r#"email_sends_total{outcome="failure",provider="postmark"}"#));
+    assert!(body.contains(r#"email_send_duration_seconds_count{provider="postmark"}"#));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn metrics_can_be_moved_to_a_separate_admin_port() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| c.application.admin_port = Some(0)).await?;
+    let admin_address = test_app.admin_address.as_deref().unwrap();
+
+    let public = get_metrics(&test_app.address).await?;
+    let admin = get_metrics(admin_address).await?;
+
+    assert_eq!(public.status().as_u16(), 404);
+    assert_eq!(admin.status().as_u16(), 200);
+    assert!(admin.text().await?.contains("http_requests_total"));
+
+    Ok(())
+}
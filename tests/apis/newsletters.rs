@@ -0,0 +1,299 @@
+use anyhow::{Context, Result};
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::common::TestApp;
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_unconfirmed_subscriber().await?;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.post_newsletters(newsletter_request_body()).await?;
+
+    assert_eq!(response.status().as_u16(), 202);
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_are_delivered_to_confirmed_subscribers() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.post_newsletters(newsletter_request_body()).await?;
+
+    assert_eq!(response.status().as_u16(), 202);
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribers_with_invalid_stored_emails_are_skipped() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, canonical_email, name, subscribed_at, status)
+        VALUES ($1, 'definitely-not-an-email', 'definitely-not-an-email', 'invalid', now(), 'confirmed')
+        "#,
+        uuid::Uuid::new_v4(),
+    )
+    .execute(&test_app.db_pool)
+    .await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.post_newsletters(newsletter_request_body()).await?;
+
+    assert_eq!(response.status().as_u16(), 202);
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn html_only_newsletters_get_inlined_css_and_a_generated_text_body() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "html": "<style>p { color: blue; }</style>\
+                    <p>Read <a href=\"https://example.com/post\">the post</a>.</p>",
+            }
+        }))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 202);
+    // The CSS is inlined once, when the issue is published.
+    let stored = sqlx::query!("SELECT html_content FROM newsletter_issues")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert!(stored
+        .html_content
+        .starts_with(r#"<p style="color: blue;">Read "#));
+    test_app.dispatch_all_pending_emails().await?;
+
+    let requests = test_app
+        .email_server
+        .received_requests()
+        .await
+        .context("No requests")?;
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)?;
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    assert!(html_body.contains(r#"<p style="color: blue;">Read "#));
+    assert!(!html_body.contains("<style>"));
+    let text_body = body["TextBody"].as_str().unwrap();
+    assert!(text_body.contains("Read [the post][1]."));
+    assert!(text_body.contains("[1]: https://example.com/post"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_unsubscribe_footer_stays_inside_the_body_of_a_full_document() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "html": "<!DOCTYPE html><html><head><style>p { color: blue; }</style></head>\
+                    <body class=\"issue\"><p>Big news</p></body></html>",
+            }
+        }))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 202);
+    test_app.dispatch_all_pending_emails().await?;
+
+    let requests = test_app
+        .email_server
+        .received_requests()
+        .await
+        .context("No requests")?;
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)?;
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    assert_eq!(html_body.matches("<body").count(), 1);
+    assert_eq!(html_body.matches("</html>").count(), 1);
+    let content = html_body.find(r#"<p style="color: blue;">Big news</p>"#);
+    let footer = html_body.find("Unsubscribe</a>");
+    let body_end = html_body.find("</body>");
+    assert!(content.is_some() && footer.is_some() && body_end.is_some());
+    assert!(content < footer && footer < body_end);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_returns_400_for_invalid_data() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let test_cases = [
+        (
+            serde_json::json!({
+                "content": {
+                    "text": "Newsletter body as plain text",
+                    "html": "<p>Newsletter body as HTML</p>",
+                }
+            }),
+            "missing title",
+        ),
+        (
+            serde_json::json!({"title": "Newsletter!"}),
+            "missing content",
+        ),
+    ];
+
+    for (body, message) in test_cases {
+        let response = test_app.post_newsletters(body).await?;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            message
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_missing_authorization_are_rejected() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/newsletters", test_app.address))
+        .json(&newsletter_request_body())
+        .send()
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.headers()["WWW-Authenticate"],
+        r#"Basic realm="publish""#
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn non_existing_user_is_rejected() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let username = uuid::Uuid::new_v4().to_string();
+    let password = uuid::Uuid::new_v4().to_string();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/newsletters", test_app.address))
+        .basic_auth(username, Some(password))
+        .json(&newsletter_request_body())
+        .send()
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.headers()["WWW-Authenticate"],
+        r#"Basic realm="publish""#
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_password_is_rejected() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let username = &test_app.test_user.username;
+    let password = uuid::Uuid::new_v4().to_string();
+    assert_ne!(test_app.test_user.password, password);
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/newsletters", test_app.address))
+        .basic_auth(username, Some(password))
+        .json(&newsletter_request_body())
+        .send()
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.headers()["WWW-Authenticate"],
+        r#"Basic realm="publish""#
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_with_the_same_idempotency_key_are_delivered_once() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+
+    for _ in 0..2 {
+        let response = test_app
+            .post_newsletters_with_idempotency_key(newsletter_request_body(), &idempotency_key)
+            .await?;
+
+        assert_eq!(response.status().as_u16(), 202);
+    }
+    test_app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+fn newsletter_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    })
+}
@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use sqlx::{Connection, PgConnection};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use zero2prod::issue_delivery_worker::run_worker_until_stopped;
+
+use crate::common::TestApp;
+
+#[tokio::test]
+async fn shutdown_stops_accepting_connections_and_closes_the_pool() -> Result<()> {
+    let mut test_app = TestApp::new().await?;
+    // Opens a connection in the application's pool.
+    assert_eq!(test_app.get("/health/ready").await?.status().as_u16(), 200);
+
+    let start = Instant::now();
+    test_app.stop().await?;
+
+    // The background worker sleeps for 10 seconds between polls of an empty queue.
+    assert!(start.elapsed() < Duration::from_secs(5));
+    let new_connection = reqwest::get(format!("{}/health_check", test_app.address)).await;
+    assert!(new_connection.is_err());
+
+    test_app.db_pool.close().await;
+    let mut connection = PgConnection::connect_with(&test_app.db_pool.connect_options()).await?;
+    let other_connections: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM pg_stat_activity \
+         WHERE datname = current_database() AND pid <> pg_backend_pid()",
+    )
+    .fetch_one(&mut connection)
+    .await?;
+    assert_eq!(other_connections, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn in_flight_requests_finish_before_shutdown() -> Result<()> {
+    let mut test_app = TestApp::new().await?;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    let shutdown_handle = test_app.shutdown_handle.clone();
+
+    let (response, ()) = tokio::join!(
+        test_app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into()),
+        async {
+            email_is_being_sent(&test_app.email_server).await;
+            shutdown_handle.trigger();
+        }
+    );
+
+    assert_eq!(response?.status().as_u16(), 200);
+    test_app.stop().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_still_running_at_the_deadline_are_dropped() -> Result<()> {
+    let mut test_app =
+        TestApp::with_configuration(|c| c.application.shutdown_deadline_seconds = 1).await?;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&test_app.email_server)
+        .await;
+    let shutdown_handle = test_app.shutdown_handle.clone();
+
+    let start = Instant::now();
+    let (response, ()) = tokio::join!(
+        test_app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into()),
+        async {
+            email_is_being_sent(&test_app.email_server).await;
+            shutdown_handle.trigger();
+        }
+    );
+    test_app.stop().await?;
+
+    assert!(response.is_err());
+    assert!(start.elapsed() < Duration::from_secs(4));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_worker_finishes_its_current_delivery_before_stopping() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    // Forgets the confirmation email, so that only the issue counts as being sent.
+    test_app.email_server.reset().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    let response = test_app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .await?;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let shutdown_handle = test_app.shutdown_handle.clone();
+    let worker = tokio::spawn(run_worker_until_stopped(
+        test_app.db_pool.clone(),
+        test_app.email_client.clone(),
+        test_app.email_templates.clone(),
+        test_app.retry_policy,
+        test_app.unsubscribe_links.clone(),
+        shutdown_handle.clone(),
+    ));
+    email_is_being_sent(&test_app.email_server).await;
+    shutdown_handle.trigger();
+    worker.await??;
+
+    let remaining: i64 = sqlx::query_scalar("SELECT count(*) FROM issue_delivery_queue")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert_eq!(remaining, 0);
+
+    Ok(())
+}
+
+/// Completes once the email server has received a request, which it then holds for its delay.
+async fn email_is_being_sent(email_server: &MockServer) {
+    while email_server.received_requests().await.unwrap().is_empty() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 use wiremock::matchers::{method, path};
 use wiremock::Mock;
+use zero2prod::subscription_token_cleanup::delete_expired_subscription_tokens;
 
 use crate::common::{ConfirmationLinks, TestApp};
 
@@ -77,3 +78,113 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() -> Result<()>
 
     Ok(())
 }
+
+#[tokio::test]
+async fn unknown_tokens_are_rejected_with_a_401() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app
+        .get("/subscriptions/confirm?subscription_token=not-a-real-token")
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/problem+json"
+    );
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["status"], 401);
+    assert_eq!(body["title"], "Unauthorized");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_tokens_are_rejected_with_a_410() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let confirmation_links = test_app.create_unconfirmed_subscriber().await?;
+    sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&test_app.db_pool)
+        .await?;
+
+    let response = reqwest::get(confirmation_links.html).await?;
+
+    assert_eq!(response.status().as_u16(), 410);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["status"], 410);
+    assert_eq!(body["title"], "Gone");
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert_eq!(saved.status, "pending_confirmation");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn following_a_used_confirmation_link_again_returns_a_200() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let confirmation_links = test_app.create_unconfirmed_subscriber().await?;
+
+    let first_response = reqwest::get(confirmation_links.html.clone()).await?;
+    let second_response = reqwest::get(confirmation_links.html).await?;
+
+    assert_eq!(first_response.status().as_u16(), 200);
+    assert_eq!(second_response.status().as_u16(), 200);
+    let token = sqlx::query!("SELECT consumed_at FROM subscription_tokens")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert!(token.consumed_at.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cleanup_deletes_unused_expired_tokens_and_subscribers_who_never_confirmed() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let body = "name=tolkien&email=jrr_tolkien%40gmail.com";
+    Mock::given(path("/email"))
+        .respond_with(wiremock::ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+    test_app
+        .post_subscriptions(body.to_string())
+        .await?
+        .error_for_status()?;
+    sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&test_app.db_pool)
+        .await?;
+
+    let outcome = delete_expired_subscription_tokens(&test_app.db_pool).await?;
+
+    // The confirmed subscriber's token was used, so it is kept.
+    assert_eq!(outcome.deleted_tokens, 1);
+    assert_eq!(outcome.deleted_subscribers, 1);
+    let remaining = sqlx::query!("SELECT email, status FROM subscriptions")
+        .fetch_all(&test_app.db_pool)
+        .await?;
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].email, "ursula_le_guin@gmail.com");
+    assert_eq!(remaining[0].status, "confirmed");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn used_confirmation_links_still_work_after_a_cleanup() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let confirmation_links = test_app.create_unconfirmed_subscriber().await?;
+    let first_response = reqwest::get(confirmation_links.html.clone()).await?;
+    assert_eq!(first_response.status().as_u16(), 200);
+    sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&test_app.db_pool)
+        .await?;
+
+    delete_expired_subscription_tokens(&test_app.db_pool).await?;
+    let second_response = reqwest::get(confirmation_links.html).await?;
+
+    assert_eq!(second_response.status().as_u16(), 200);
+
+    Ok(())
+}
@@ -5,6 +5,9 @@ use wiremock::{Mock, ResponseTemplate};
 use crate::common::{ConfirmationLinks, TestApp};
 
 mod confirm;
+mod negotiation;
+mod rate_limit;
+mod unsubscribe;
 
 #[tokio::test]
 async fn subscribe_returns_a_200_for_valid_form_data() -> Result<()> {
@@ -140,3 +143,312 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn subscribing_twice_while_pending_resends_the_confirmation_email() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&test_app.email_server)
+        .await;
+
+    let first_response = test_app.post_subscriptions(body.to_string()).await?;
+    let second_response = test_app.post_subscriptions(body.to_string()).await?;
+
+    assert_eq!(first_response.status().as_u16(), 200);
+    assert_eq!(second_response.status().as_u16(), 200);
+
+    let requests = test_app
+        .email_server
+        .received_requests()
+        .await
+        .context("No requests")?;
+    let second_links = ConfirmationLinks::try_from(&requests[1], test_app.port)?;
+
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&test_app.db_pool)
+        .await?
+        .count;
+    assert_eq!(n_subscribers, 1);
+
+    // The link from the second email confirms the subscription.
+    reqwest::get(second_links.html).await?.error_for_status()?;
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert_eq!(saved.status, "confirmed");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribing_again_once_confirmed_returns_a_200_without_sending_an_email() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.post_subscriptions(body.to_string()).await?;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_all(&test_app.db_pool)
+        .await?;
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].status, "confirmed");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn addresses_that_only_differ_in_case_belong_to_the_same_subscriber() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&test_app.email_server)
+        .await;
+
+    test_app
+        .post_subscriptions("name=le%20guin&email=Ursula_Le_Guin%40Gmail.COM".to_string())
+        .await?
+        .error_for_status()?;
+    test_app
+        .post_subscriptions("name=le%20guin&email=%20ursula_le_guin%40gmail.com%20".to_string())
+        .await?
+        .error_for_status()?;
+
+    let saved = sqlx::query!("SELECT email, canonical_email FROM subscriptions")
+        .fetch_all(&test_app.db_pool)
+        .await?;
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved[0].canonical_email, "ursula_le_guin@gmail.com");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn internationalized_domains_are_stored_as_punycode() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+
+    test_app
+        .post_subscriptions_json(&serde_json::json!({
+            "name": "le guin",
+            "email": "Ursula@Bücher.example",
+        }))
+        .await?
+        .error_for_status()?;
+
+    let saved = sqlx::query!("SELECT email, canonical_email FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert_eq!(saved.email, "Ursula@xn--bcher-kva.example");
+    assert_eq!(saved.canonical_email, "ursula@xn--bcher-kva.example");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn disposable_email_domains_are_rejected() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_subscriptions("name=le%20guin&email=ursula%40Mailinator.com".to_string())
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(
+        body["errors"],
+        serde_json::json!([{
+            "field": "email",
+            "rule": "disposable_domain",
+            "domain": "mailinator.com",
+            "message": "must not use the disposable email domain mailinator.com",
+        }])
+    );
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&test_app.db_pool)
+        .await?
+        .count;
+    assert_eq!(n_subscribers, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn addresses_on_undeliverable_domains_are_rejected() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| {
+        c.email_validation.domain_verification.undeliverable_domains = vec!["gmial.com".into()];
+    })
+    .await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40GMIAL.com".to_string())
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(
+        body["errors"],
+        serde_json::json!([{
+            "field": "email",
+            "rule": "undeliverable_domain",
+            "domain": "gmial.com",
+            "message": "uses the domain gmial.com, which does not accept email",
+            "suggestion": "ursula_le_guin@gmail.com",
+        }])
+    );
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&test_app.db_pool)
+        .await?
+        .count;
+    assert_eq!(n_subscribers, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_addresses_on_a_misspelt_popular_domain_get_a_suggestion() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app
+        .post_subscriptions_json(&serde_json::json!({
+            "name": "",
+            "email": "Ursula@gmail..com",
+        }))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["errors"][0]["field"], "email");
+    assert_eq!(body["errors"][0]["rule"], "invalid_email");
+    assert_eq!(body["errors"][0]["suggestion"], "Ursula@gmail.com");
+    // Suggestions only concern the email.
+    assert_eq!(body["errors"][1]["field"], "name");
+    assert!(body["errors"][1].get("suggestion").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn suggestions_are_rendered_in_every_format() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let post = |accept: &'static str| {
+        reqwest::Client::new()
+            .post(format!("{}/subscriptions", test_app.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", accept)
+            .body("name=le%20guin&email=ursula%40hotmial..com")
+            .send()
+    };
+
+    let text = post("text/plain").await?.text().await?;
+    assert!(
+        text.contains("email: is not a valid email address (did you mean ursula@hotmail.com?)\n")
+    );
+
+    let html = post("text/html").await?.text().await?;
+    assert!(html.contains(
+        "<li>email: is not a valid email address (did you mean ursula@hotmail.com?)</li>"
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn valid_addresses_get_no_suggestion() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app
+        .post_subscriptions_json(&serde_json::json!({
+            "name": "<b>",
+            "email": "ursula@gmial.com",
+        }))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await?;
+    let errors = body["errors"].as_array().unwrap();
+    assert!(errors.iter().all(|error| error["field"] == "name"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_subscriptions_are_described_by_a_problem_details_body() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app
+        .post_subscriptions("name=Ursula&email=definitely-not-an-email".to_string())
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/problem+json"
+    );
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["type"], "about:blank");
+    assert_eq!(body["title"], "Bad Request");
+    assert_eq!(body["status"], 400);
+    assert!(body["detail"].is_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_500_problem_when_the_confirmation_email_cannot_be_sent() -> Result<()>
+{
+    let test_app = TestApp::new().await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".to_string())
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 500);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["status"], 500);
+    assert_eq!(body["detail"], "Failed to send the confirmation email.");
+
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use anyhow::Result;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::common::TestApp;
+
+#[tokio::test]
+async fn subscribe_accepts_json() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_subscriptions_json(&json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+        }))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT email, name FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.name, "le guin");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn every_invalid_field_is_listed() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app
+        .post_subscriptions_json(&json!({
+            "name": "",
+            "email": "definitely-not-an-email",
+        }))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/problem+json"
+    );
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["detail"], "The subscription request is invalid.");
+    let fields: Vec<_> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["email", "name"]);
+    assert_eq!(body["errors"][0]["rule"], "invalid_email");
+    assert_eq!(body["errors"][0]["message"], "is not a valid email address");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn every_broken_rule_is_listed_with_its_parameters() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app
+        .post_subscriptions_json(&json!({
+            "name": format!("<{}>", "a".repeat(256)),
+            "email": "ursula_le_guin@gmail.com",
+        }))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(
+        body["errors"],
+        json!([
+            {
+                "field": "name",
+                "rule": "too_long",
+                "max": 256,
+                "message": "must be at most 256 characters long",
+            },
+            {
+                "field": "name",
+                "rule": "forbidden_character",
+                "character": "<",
+                "message": "must not contain '<'",
+            },
+            {
+                "field": "name",
+                "rule": "forbidden_character",
+                "character": ">",
+                "message": "must not contain '>'",
+            },
+        ])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_fields_are_listed_as_invalid() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = test_app
+        .post_subscriptions_json(&json!({"name": "Ursula"}))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["errors"][0]["field"], "email");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn validation_errors_follow_the_accept_header() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    let client = reqwest::Client::new();
+    let post = |accept: &'static str| {
+        client
+            .post(format!("{}/subscriptions", test_app.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", accept)
+            .body("name=%3Cb%3E&email=definitely-not-an-email")
+            .send()
+    };
+
+    let response = post("text/html,application/xhtml+xml;q=0.9,*/*;q=0.8").await?;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "text/html; charset=utf-8"
+    );
+    let html = response.text().await?;
+    assert!(html.contains("<li>email: is not a valid email address</li>"));
+    assert!(html.contains("<li>name: must not contain &#x27;&lt;&#x27;</li>"));
+
+    let response = post("text/plain").await?;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "text/plain; charset=utf-8"
+    );
+    let text = response.text().await?;
+    assert!(text.contains("email: is not a valid email address\n"));
+    assert!(text.contains("name: must not contain '<'\n"));
+
+    let response = post("image/png, application/problem+json;q=0.5").await?;
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/problem+json"
+    );
+
+    let response = post("image/png").await?;
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/problem+json"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsupported_content_types_are_rejected_with_a_415() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/subscriptions", test_app.address))
+        .header("Content-Type", "text/plain")
+        .body("ursula_le_guin@gmail.com")
+        .send()
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 415);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["status"], 415);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn malformed_json_is_rejected_with_a_400() -> Result<()> {
+    let test_app = TestApp::new().await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/subscriptions", test_app.address))
+        .header("Content-Type", "application/json")
+        .body(r#"{"name": "Ursula","#)
+        .send()
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await?;
+    assert!(body["detail"]
+        .as_str()
+        .unwrap()
+        .contains("Json deserialize error"));
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::common::TestApp;
+
+async fn post_subscriptions_via_proxy(
+    test_app: &TestApp,
+    body: &str,
+    forwarded_for: &str,
+) -> Result<reqwest::Response> {
+    let response = reqwest::Client::new()
+        .post(format!("{}/subscriptions", test_app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("X-Forwarded-For", forwarded_for)
+        .body(body.to_string())
+        .send()
+        .await?;
+
+    Ok(response)
+}
+
+fn subscription_body(n: usize) -> String {
+    format!("name=le%20guin&email=ursula_{}%40gmail.com", n)
+}
+
+async fn accept_all_emails(test_app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn repeated_subscriptions_for_one_email_are_rate_limited() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| {
+        c.application.rate_limit.per_email.capacity = 2;
+    })
+    .await?;
+    accept_all_emails(&test_app).await;
+
+    for _ in 0..2 {
+        let response = test_app.post_subscriptions(subscription_body(0)).await?;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = test_app.post_subscriptions(subscription_body(0)).await?;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("Retry-After"));
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["status"], 429);
+    let emails_sent = test_app.email_server.received_requests().await.unwrap();
+    assert_eq!(emails_sent.len(), 2);
+
+    // Other addresses are unaffected.
+    let response = test_app.post_subscriptions(subscription_body(1)).await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_from_one_ip_are_rate_limited() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| {
+        c.application.rate_limit.per_ip.capacity = 2;
+        c.application.rate_limit.per_ip.refill_interval_seconds = 60;
+    })
+    .await?;
+    accept_all_emails(&test_app).await;
+
+    for n in 0..2 {
+        let response = test_app.post_subscriptions(subscription_body(n)).await?;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = test_app.post_subscriptions(subscription_body(2)).await?;
+
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after: u64 = response.headers()["Retry-After"].to_str()?.parse()?;
+    assert!((1..=60).contains(&retry_after));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn forwarded_for_identifies_clients_behind_a_trusted_proxy() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| {
+        c.application.rate_limit.per_ip.capacity = 1;
+        c.application.rate_limit.trusted_proxies = vec!["127.0.0.1/32".parse().unwrap()];
+    })
+    .await?;
+    accept_all_emails(&test_app).await;
+
+    for (n, client) in ["203.0.113.1", "203.0.113.2"].into_iter().enumerate() {
+        let response =
+            post_subscriptions_via_proxy(&test_app, &subscription_body(n), client).await?;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response =
+        post_subscriptions_via_proxy(&test_app, &subscription_body(2), "203.0.113.1").await?;
+    assert_eq!(response.status().as_u16(), 429);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn forwarded_for_is_ignored_without_a_trusted_proxy() -> Result<()> {
+    let test_app = TestApp::with_configuration(|c| {
+        c.application.rate_limit.per_ip.capacity = 1;
+    })
+    .await?;
+    accept_all_emails(&test_app).await;
+
+    let response =
+        post_subscriptions_via_proxy(&test_app, &subscription_body(0), "203.0.113.1").await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response =
+        post_subscriptions_via_proxy(&test_app, &subscription_body(1), "203.0.113.2").await?;
+    assert_eq!(response.status().as_u16(), 429);
+
+    Ok(())
+}
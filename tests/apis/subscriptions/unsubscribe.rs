@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::common::TestApp;
+
+#[tokio::test]
+async fn newsletter_issues_carry_one_click_unsubscribe_headers() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let subscriber_id = subscriber_id(&test_app).await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_issue(&test_app).await?;
+
+    let requests = test_app
+        .email_server
+        .received_requests()
+        .await
+        .context("No requests")?;
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)?;
+    assert_eq!(
+        body["Headers"],
+        serde_json::json!([
+            {
+                "Name": "List-Unsubscribe",
+                "Value": format!("<{}>", test_app.unsubscribe_links.link(subscriber_id)),
+            },
+            {
+                "Name": "List-Unsubscribe-Post",
+                "Value": "List-Unsubscribe=One-Click",
+            },
+        ])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletter_issues_end_with_an_unsubscribe_link() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let link = test_app
+        .unsubscribe_links
+        .link(subscriber_id(&test_app).await?);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_issue(&test_app).await?;
+
+    let requests = test_app
+        .email_server
+        .received_requests()
+        .await
+        .context("No requests")?;
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)?;
+    assert!(body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains(&format!(r#"<a href="{}">Unsubscribe</a>"#, link)));
+    assert!(body["TextBody"].as_str().unwrap().contains(&link));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_unsubscribe_link_shows_a_confirmation_form() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let token = test_app
+        .unsubscribe_links
+        .token(subscriber_id(&test_app).await?);
+
+    let response = test_app
+        .get(&format!("/subscriptions/unsubscribe?token={}", token))
+        .await?;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await?;
+    assert!(html_page.contains(r#"method="post""#));
+    assert!(html_page.contains(&token));
+
+    // Following the link alone does not unsubscribe anybody.
+    assert_eq!(subscriber_status(&test_app).await?, "confirmed");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_marks_the_subscriber_as_unsubscribed() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let token = test_app
+        .unsubscribe_links
+        .token(subscriber_id(&test_app).await?);
+
+    let response = test_app.post_unsubscribe(&token).await?;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&test_app).await?, "unsubscribed");
+
+    // Unsubscribing twice is harmless.
+    let response = test_app.post_unsubscribe(&token).await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_do_not_receive_newsletter_issues() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let token = test_app
+        .unsubscribe_links
+        .token(subscriber_id(&test_app).await?);
+    test_app.post_unsubscribe(&token).await?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_issue(&test_app).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pending_deliveries_are_dropped_when_unsubscribing() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let token = test_app
+        .unsubscribe_links
+        .token(subscriber_id(&test_app).await?);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // A failed attempt leaves the delivery in the queue, waiting for a retry.
+    publish_issue(&test_app).await?;
+    test_app.post_unsubscribe(&token).await?;
+
+    let pending = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&test_app.db_pool)
+        .await?
+        .count;
+    assert_eq!(pending, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_unsubscribe_tokens_are_rejected_with_a_401() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let other_token = test_app.unsubscribe_links.token(Uuid::new_v4());
+    let subscriber_token = test_app
+        .unsubscribe_links
+        .token(subscriber_id(&test_app).await?);
+    // The subscriber's id signed as if it were somebody else's.
+    let forged_token = format!(
+        "{}.{}",
+        subscriber_token.split_once('.').unwrap().0,
+        other_token.split_once('.').unwrap().1
+    );
+
+    for token in ["not-a-token", forged_token.as_str()] {
+        let response = test_app
+            .get(&format!("/subscriptions/unsubscribe?token={}", token))
+            .await?;
+        assert_eq!(response.status().as_u16(), 401);
+
+        let response = test_app.post_unsubscribe(token).await?;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    assert_eq!(subscriber_status(&test_app).await?, "confirmed");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_can_subscribe_again() -> Result<()> {
+    let test_app = TestApp::new().await?;
+    test_app.create_confirmed_subscriber().await?;
+    let token = test_app
+        .unsubscribe_links
+        .token(subscriber_id(&test_app).await?);
+    test_app.post_unsubscribe(&token).await?;
+
+    test_app.create_confirmed_subscriber().await?;
+
+    assert_eq!(subscriber_status(&test_app).await?, "confirmed");
+
+    Ok(())
+}
+
+async fn subscriber_id(test_app: &TestApp) -> Result<Uuid> {
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+
+    Ok(subscriber.id)
+}
+
+async fn subscriber_status(test_app: &TestApp) -> Result<String> {
+    let subscriber = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await?;
+
+    Ok(subscriber.status)
+}
+
+async fn publish_issue(test_app: &TestApp) -> Result<()> {
+    let response = test_app
+        .post_newsletters(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .await?;
+    assert_eq!(response.status().as_u16(), 202);
+
+    test_app.dispatch_all_pending_emails().await
+}